@@ -0,0 +1,65 @@
+// Weighted choices for arrays and alternation branches, sampled in constant
+// time with Vose's alias method.
+
+use rand::Rng;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct AliasTable {
+    /// The weights the table was built from, kept to print the pattern.
+    pub(crate) weights: Vec<f64>,
+    probability: Vec<f64>,
+    alias: Vec<usize>,
+}
+
+// Weights are checked to be finite, so they are never NaN.
+impl Eq for AliasTable {}
+
+impl AliasTable {
+    /// Builds the table, or returns `None` unless the weights are finite,
+    /// non-negative and not all zero.
+    pub(crate) fn new(weights: Vec<f64>) -> Option<AliasTable> {
+        if weights.iter().any(|weight| !weight.is_finite() || *weight < 0.0) {
+            return None;
+        }
+        let total: f64 = weights.iter().sum();
+        if !total.is_finite() || total <= 0.0 {
+            return None;
+        }
+        let n = weights.len();
+        let mut probability: Vec<f64> = weights.iter().map(|weight| weight * n as f64 / total).collect();
+        let mut alias: Vec<usize> = (0..n).collect();
+        let (mut small, mut large): (Vec<usize>, Vec<usize>) = (0..n).partition(|&i| probability[i] < 1.0);
+        while let (Some(&less), Some(&more)) = (small.last(), large.last()) {
+            small.pop();
+            alias[less] = more;
+            probability[more] -= 1.0 - probability[less];
+            if probability[more] < 1.0 {
+                large.pop();
+                small.push(more);
+            }
+        }
+        // What is left is 1 up to rounding errors.
+        for i in small.into_iter().chain(large) {
+            probability[i] = 1.0;
+        }
+        Some(AliasTable { weights, probability, alias })
+    }
+
+    /// An index with probability proportional to its weight.
+    pub(crate) fn sample<R: Rng>(&self, rng: &mut R) -> usize {
+        let i = rng.gen_range(0..self.probability.len());
+        if rng.gen::<f64>() < self.probability[i] { i } else { self.alias[i] }
+    }
+}
+
+/// Parses a weight: a non-negative decimal number.
+pub(crate) fn parse_weight(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let is_number = !text.is_empty()
+        && text.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && text.chars().filter(|&c| c == '.').count() <= 1;
+    if !is_number {
+        return None;
+    }
+    text.parse().ok().filter(|weight: &f64| weight.is_finite())
+}
@@ -0,0 +1,206 @@
+// Reading array values from `-a` lists and from dictionary files.
+
+use crate::alias;
+use crate::error::Error;
+
+/// Splits a comma-separated list of array values, as given to `-a`.
+///
+/// A value wrapped in double quotes may contain commas, with `""` for a
+/// quote inside it; elsewhere a backslash takes the next character
+/// literally, so `"a, b",c\,d` gives `a, b` and `c,d`.
+pub fn parse_array_list(list: &str) -> Result<Vec<String>, Error> {
+    let fields = split_fields(list, true).map_err(|err| Error::InvalidArray(format!("{} in '{}'", err, list)))?;
+    Ok(fields.into_iter().map(|(field, _)| field).collect())
+}
+
+/// Splits a comma-separated list of patterns for a pattern array. Commas
+/// inside `()`, `[]` and `{}` belong to the pattern, as in `\d{1,3}`, and
+/// backslashes are kept for the pattern to read, so `\,` is a literal comma.
+pub fn parse_pattern_list(list: &str) -> Vec<String> {
+    let mut patterns = Vec::new();
+    let mut pattern = String::new();
+    let mut chars = list.chars();
+    let (mut depth, mut class) = (0usize, false);
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => {
+                pattern.push(ch);
+                pattern.extend(chars.next());
+                continue;
+            }
+            ']' if class => class = false,
+            _ if class => {}
+            '[' => class = true,
+            '(' | '{' => depth += 1,
+            ')' | '}' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                patterns.push(std::mem::take(&mut pattern));
+                continue;
+            }
+            _ => {}
+        }
+        pattern.push(ch);
+    }
+    patterns.push(pattern);
+    patterns
+}
+
+/// Splits a list like [`parse_array_list`], reading a `:WEIGHT` suffix as
+/// the weight of a value, as in `paid:80,pending:15,refunded:5`. Values
+/// without one weigh 1; a quoted or escaped colon is part of the value.
+/// The weights are `None` when no value has one.
+pub fn parse_weighted_array_list(list: &str) -> Result<(Vec<String>, Option<Vec<f64>>), Error> {
+    let fields = split_fields(list, true).map_err(|err| Error::InvalidArray(format!("{} in '{}'", err, list)))?;
+    let mut values = Vec::new();
+    let mut weights = Vec::new();
+    for (mut field, colon) in fields {
+        let weight = colon.and_then(|colon| alias::parse_weight(&field[colon + 1..]).map(|weight| (colon, weight)));
+        match weight {
+            Some((colon, weight)) => {
+                field.truncate(colon);
+                weights.push(Some(weight));
+            }
+            None => weights.push(None),
+        }
+        values.push(field);
+    }
+    let weights = weights.iter().any(Option::is_some).then(|| weights.iter().map(|weight| weight.unwrap_or(1.0)).collect());
+    Ok((values, weights))
+}
+
+/// Reads array values from the text of a file, one per line. Empty lines and
+/// lines starting with `#` are skipped.
+///
+/// With `column`, each line is a CSV record and the value comes from that
+/// column: a 1-based number, or the name of a column in the first record,
+/// which is then read as the header.
+pub fn parse_array_file(text: &str, column: Option<&str>) -> Result<Vec<String>, Error> {
+    let Some(column) = column else {
+        return Ok(lines(text).map(|(_, line)| line.to_string()).collect());
+    };
+    let mut records = read_columns(text, &[column])?;
+    Ok(records.iter_mut().map(|record| record.swap_remove(0)).collect())
+}
+
+/// Reads values from `column` of a CSV file like [`parse_array_file`],
+/// each weighted by the number in `weight_column`.
+pub fn parse_weighted_array_file(text: &str, column: &str, weight_column: &str) -> Result<(Vec<String>, Vec<f64>), Error> {
+    let mut values = Vec::new();
+    let mut weights = Vec::new();
+    for mut record in read_columns(text, &[column, weight_column])? {
+        let weight = record.pop().unwrap();
+        let weight = alias::parse_weight(&weight)
+            .ok_or_else(|| Error::InvalidArray(format!("'{}' is not a weight in '{}'", weight, record[0])))?;
+        values.push(record.pop().unwrap());
+        weights.push(weight);
+    }
+    Ok((values, weights))
+}
+
+/// Reads a CSV file for a table: the column names from the first record and
+/// a row from each record after it, skipping lines like [`parse_array_file`].
+pub fn parse_table(text: &str) -> Result<(Vec<String>, Vec<Vec<String>>), Error> {
+    let invalid = |number: usize, message: String| Error::InvalidArray(format!("line {}: {}", number + 1, message));
+    let mut lines = lines(text);
+    let Some((number, header)) = lines.next() else {
+        return Ok((Vec::new(), Vec::new()));
+    };
+    let columns: Vec<String> = split_record(header, false)
+        .map_err(|err| invalid(number, err))?
+        .into_iter()
+        .map(|column| column.trim().to_string())
+        .collect();
+    let rows = lines
+        .map(|(number, line)| {
+            let row = split_record(line, false).map_err(|err| invalid(number, err))?;
+            if row.len() != columns.len() {
+                return Err(invalid(number, format!("{} values for {} columns", row.len(), columns.len())));
+            }
+            Ok(row)
+        })
+        .collect::<Result<_, _>>()?;
+    Ok((columns, rows))
+}
+
+/// The lines of a file that hold values, numbered from 0.
+fn lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+}
+
+/// Reads `columns` out of each CSV record. Named columns make the first
+/// record the header.
+fn read_columns(text: &str, columns: &[&str]) -> Result<Vec<Vec<String>>, Error> {
+    let invalid = |number: usize, message: String| Error::InvalidArray(format!("line {}: {}", number + 1, message));
+    let mut lines = lines(text).peekable();
+    let mut header = None;
+    if columns.iter().any(|column| column.parse::<usize>().is_err()) {
+        let Some((number, line)) = lines.next() else {
+            return Ok(Vec::new());
+        };
+        header = Some((number, split_record(line, false).map_err(|err| invalid(number, err))?));
+    }
+    let indexes = columns
+        .iter()
+        .map(|column| match column.parse::<usize>() {
+            Ok(0) => Err(Error::InvalidArray("columns are numbered from 1".to_string())),
+            Ok(number) => Ok(number - 1),
+            Err(_) => {
+                let (number, header) = header.as_ref().unwrap();
+                header
+                    .iter()
+                    .position(|name| name.trim() == *column)
+                    .ok_or_else(|| invalid(*number, format!("no column named '{}'", column)))
+            }
+        })
+        .collect::<Result<Vec<usize>, Error>>()?;
+    lines
+        .map(|(number, line)| {
+            let record = split_record(line, false).map_err(|err| invalid(number, err))?;
+            indexes
+                .iter()
+                .map(|&index| record.get(index).cloned().ok_or_else(|| invalid(number, format!("no column {}", index + 1))))
+                .collect()
+        })
+        .collect()
+}
+
+/// Splits one CSV record. `backslash` also lets a backslash escape the next
+/// character outside quotes.
+pub(crate) fn split_record(line: &str, backslash: bool) -> Result<Vec<String>, String> {
+    Ok(split_fields(line, backslash)?.into_iter().map(|(field, _)| field).collect())
+}
+
+/// Splits a record into fields, each with the byte offset of its last colon
+/// that is neither quoted nor escaped.
+fn split_fields(line: &str, backslash: bool) -> Result<Vec<(String, Option<usize>)>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut colon = None;
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            '\\' if backslash && !quoted => field.push(chars.next().unwrap_or('\\')),
+            ',' if !quoted => fields.push((std::mem::take(&mut field), colon.take())),
+            ':' if !quoted => {
+                colon = Some(field.len());
+                field.push(ch);
+            }
+            _ => field.push(ch),
+        }
+    }
+    if quoted {
+        return Err("unterminated quote".to_string());
+    }
+    fields.push((field, colon));
+    Ok(fields)
+}
@@ -0,0 +1,443 @@
+use crate::error::Error;
+use std::fmt;
+
+/// What a counter does when a step would take it past `min` or `max`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnBound {
+    /// Generation fails with [`Error::CounterOutOfRange`].
+    #[default]
+    Stop,
+    /// Continue from the opposite bound, as in 9998, 9999, 0, 1. A missing
+    /// `min` counts as 0. A `max` is required: [`Counter::parse`] rejects
+    /// `wrap` without one, and a counter that ends up without one fails
+    /// like [`OnBound::Stop`].
+    Wrap,
+    /// Stay at the bound.
+    Clamp,
+}
+
+/// When `\i` moves a counter on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CounterMode {
+    /// Every `\i` advances its counter, so `\i,\i` gives `1,2` (the default).
+    #[default]
+    PerOccurrence,
+    /// Each counter advances once per `generate` call, at its first `\i` or
+    /// `\I`, and every later use in the same call repeats that value, so
+    /// `\i,\i` gives `1,1` and then `2,2`. A `\I` that comes first steps
+    /// with the options of the counter's first `\i` in the pattern.
+    PerCall,
+}
+
+/// The digits a counter counts in, from zero upwards. The first digit
+/// doubles as the padding character, so with [`Alphabet::LettersUpper`]
+/// `AZ` is followed by `BA` and `ZZ` by `BAA`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Alphabet {
+    /// `0-9`
+    #[default]
+    Decimal,
+    /// `0-9a-f`
+    Hex,
+    /// `0-9A-F`
+    HexUpper,
+    /// `0-9a-z`
+    Base36,
+    /// `0-9A-Z`
+    Base36Upper,
+    /// `0-9A-Za-z`
+    Base62,
+    /// `a-z`
+    Letters,
+    /// `A-Z`
+    LettersUpper,
+    /// The given characters, each used once, in ascending order.
+    Custom(String),
+}
+
+impl Alphabet {
+    /// Looks up an alphabet by the name used in counter specs: `decimal`,
+    /// `hex`, `HEX`, `base36`, `BASE36`, `base62`, `letters` or `LETTERS`.
+    /// Any other string of at least two distinct characters is a custom
+    /// alphabet.
+    pub fn parse(name: &str) -> Option<Alphabet> {
+        let alphabet = match name {
+            "decimal" => Alphabet::Decimal,
+            "hex" => Alphabet::Hex,
+            "HEX" => Alphabet::HexUpper,
+            "base36" => Alphabet::Base36,
+            "BASE36" => Alphabet::Base36Upper,
+            "base62" => Alphabet::Base62,
+            "letters" => Alphabet::Letters,
+            "LETTERS" => Alphabet::LettersUpper,
+            custom => {
+                let chars: Vec<char> = custom.chars().collect();
+                let distinct = chars.iter().enumerate().all(|(i, ch)| !chars[..i].contains(ch));
+                if chars.len() < 2 || !distinct {
+                    return None;
+                }
+                Alphabet::Custom(custom.to_string())
+            }
+        };
+        Some(alphabet)
+    }
+
+    fn digits(&self) -> Vec<char> {
+        let ranges: &[(char, char)] = match self {
+            Alphabet::Decimal => &[('0', '9')],
+            Alphabet::Hex => &[('0', '9'), ('a', 'f')],
+            Alphabet::HexUpper => &[('0', '9'), ('A', 'F')],
+            Alphabet::Base36 => &[('0', '9'), ('a', 'z')],
+            Alphabet::Base36Upper => &[('0', '9'), ('A', 'Z')],
+            Alphabet::Base62 => &[('0', '9'), ('A', 'Z'), ('a', 'z')],
+            Alphabet::Letters => &[('a', 'z')],
+            Alphabet::LettersUpper => &[('A', 'Z')],
+            Alphabet::Custom(chars) => return chars.chars().collect(),
+        };
+        ranges.iter().flat_map(|&(first, last)| first..=last).collect()
+    }
+}
+
+/// Writes the name [`Alphabet::parse`] accepts.
+impl fmt::Display for Alphabet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Alphabet::Decimal => "decimal",
+            Alphabet::Hex => "hex",
+            Alphabet::HexUpper => "HEX",
+            Alphabet::Base36 => "base36",
+            Alphabet::Base36Upper => "BASE36",
+            Alphabet::Base62 => "base62",
+            Alphabet::Letters => "letters",
+            Alphabet::LettersUpper => "LETTERS",
+            Alphabet::Custom(chars) => chars,
+        })
+    }
+}
+
+/// Settings and current value of a counter used by `\i` and `\i{name}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counter {
+    /// The last value handed out; the next use continues from here.
+    pub value: String,
+    /// 1 for ascending, -1 for descending. A `+` or `-` after `\i` in the
+    /// pattern takes precedence.
+    pub direction: i32,
+    /// Zero-pad the number to this many digits, unless the pattern gives
+    /// its own `{:width}`.
+    pub width: Option<usize>,
+    /// How much each use adds or subtracts.
+    pub step: u64,
+    pub min: Option<i128>,
+    pub max: Option<i128>,
+    pub on_bound: OnBound,
+    pub alphabet: Alphabet,
+}
+
+/// Per-occurrence settings written in the pattern, e.g. `\i+{order:6,step=10}`.
+/// Each one that is set overrides the counter's own.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct CounterOptions {
+    pub(crate) direction: Option<i32>,
+    pub(crate) width: Option<usize>,
+    pub(crate) step: Option<u64>,
+    pub(crate) min: Option<i128>,
+    pub(crate) max: Option<i128>,
+    pub(crate) on_bound: Option<OnBound>,
+    pub(crate) alphabet: Option<Alphabet>,
+}
+
+impl CounterOptions {
+    /// Applies one `key=value` or flag item, returning false if it is not a
+    /// counter option. `asc` and `desc` are only accepted when
+    /// `allow_direction` is set, since the pattern uses `+` and `-` instead.
+    pub(crate) fn apply(&mut self, item: &str, allow_direction: bool) -> bool {
+        let item = item.trim();
+        match item.split_once('=') {
+            None => match item {
+                "asc" if allow_direction => self.direction = Some(1),
+                "desc" if allow_direction => self.direction = Some(-1),
+                "stop" => self.on_bound = Some(OnBound::Stop),
+                "wrap" => self.on_bound = Some(OnBound::Wrap),
+                "clamp" => self.on_bound = Some(OnBound::Clamp),
+                _ => return false,
+            },
+            Some((key, value)) => {
+                let value = value.trim();
+                let parsed = match key.trim() {
+                    "width" => value.parse().map(|width| self.width = Some(width)).is_ok(),
+                    "step" => value.parse().map(|step| self.step = Some(step)).is_ok(),
+                    "min" => value.parse().map(|min| self.min = Some(min)).is_ok(),
+                    "max" => value.parse().map(|max| self.max = Some(max)).is_ok(),
+                    "alphabet" => Alphabet::parse(value).map(|alphabet| self.alphabet = Some(alphabet)).is_some(),
+                    _ => false,
+                };
+                return parsed;
+            }
+        }
+        true
+    }
+
+    /// Whether the options work together: `wrap` needs a `max` to wrap at.
+    pub(crate) fn is_consistent(&self) -> bool {
+        self.on_bound != Some(OnBound::Wrap) || self.max.is_some()
+    }
+}
+
+/// Writes the options as `,key=value` items, in the form the pattern accepts.
+impl fmt::Display for CounterOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(step) = self.step {
+            write!(f, ",step={}", step)?;
+        }
+        if let Some(min) = self.min {
+            write!(f, ",min={}", min)?;
+        }
+        if let Some(max) = self.max {
+            write!(f, ",max={}", max)?;
+        }
+        match self.on_bound {
+            Some(OnBound::Stop) => f.write_str(",stop")?,
+            Some(OnBound::Wrap) => f.write_str(",wrap")?,
+            Some(OnBound::Clamp) => f.write_str(",clamp")?,
+            None => {}
+        }
+        if let Some(alphabet) = &self.alphabet {
+            write!(f, ",alphabet={}", alphabet)?;
+        }
+        Ok(())
+    }
+}
+
+impl Counter {
+    /// An ascending counter with step 1 and no bounds, whose first use
+    /// yields the value after `start`.
+    pub fn new(start: &str) -> Self {
+        Self {
+            value: start.to_string(),
+            direction: 1,
+            width: None,
+            step: 1,
+            min: None,
+            max: None,
+            on_bound: OnBound::default(),
+            alphabet: Alphabet::default(),
+        }
+    }
+
+    /// Parses a `[NAME=]START[,OPTION...]` spec as taken by the `-i` command
+    /// line option. Options are `asc`, `desc`, `width=N`, `step=N`, `min=N`,
+    /// `max=N`, one of `stop`, `wrap` or `clamp`, and `alphabet=NAME` as
+    /// taken by [`Alphabet::parse`]. `wrap` needs a `max`. An empty name
+    /// stands for the counter used by a plain `\i`.
+    pub fn parse(spec: &str) -> Result<(String, Counter), Error> {
+        let mut parts = spec.split(',');
+        let first = parts.next().unwrap_or_default();
+        let (name, start) = match first.split_once('=') {
+            Some((name, start)) if is_valid_name(name) => (name, start),
+            _ => ("", first),
+        };
+        let mut options = CounterOptions::default();
+        for option in parts {
+            if !options.apply(option, true) {
+                return Err(Error::InvalidCounter(spec.to_string()));
+            }
+        }
+        if !options.is_consistent() {
+            return Err(Error::InvalidCounter(spec.to_string()));
+        }
+        let counter = Counter::new(start);
+        Ok((name.to_string(), counter.with_options(&options)))
+    }
+
+    fn with_options(&self, options: &CounterOptions) -> Counter {
+        Counter {
+            value: self.value.clone(),
+            direction: options.direction.unwrap_or(self.direction),
+            width: options.width.or(self.width),
+            step: options.step.unwrap_or(self.step),
+            min: options.min.or(self.min),
+            max: options.max.or(self.max),
+            on_bound: options.on_bound.unwrap_or(self.on_bound),
+            alphabet: options.alphabet.clone().unwrap_or_else(|| self.alphabet.clone()),
+        }
+    }
+
+    /// Moves the counter one step, with `options` from the pattern taking
+    /// precedence, and returns the new value.
+    ///
+    /// The last run of characters from the counter's alphabet is the
+    /// number; the text before and after it is kept. A `-` right before the
+    /// number at the very start of the value is a minus sign. The number may
+    /// have any number of digits, and a leading zero digit keeps the run at
+    /// least as wide as it was, so `INV0099-EU` becomes `INV0100-EU`.
+    pub(crate) fn advance(&mut self, name: &str, options: &CounterOptions) -> Result<String, Error> {
+        let settings = self.with_options(options);
+        let alphabet = settings.alphabet.digits();
+        let base = alphabet.len() as u32;
+        let chars: Vec<char> = self.value.chars().collect();
+        let Some(end) = chars.iter().rposition(|ch| alphabet.contains(ch)).map(|last| last + 1) else {
+            return Ok(self.value.clone());
+        };
+        let start = chars[..end].iter().rposition(|ch| !alphabet.contains(ch)).map_or(0, |last| last + 1);
+        let digits: Vec<u32> = chars[start..end].iter().map(|ch| alphabet.iter().position(|c| c == ch).unwrap() as u32).collect();
+        let (prefix, negative) = match &chars[..start] {
+            ['-'] => (String::new(), true),
+            prefix => (prefix.iter().collect(), false),
+        };
+
+        let step = settings.step as i128 * settings.direction.signum() as i128;
+        let number = Number::parse(negative, &digits, base).add(step, base);
+        let (negative, magnitude) = settings.bound(name, number, base)?.into_digits(base);
+        let width = settings.width.unwrap_or(if digits[0] == 0 { digits.len() } else { 0 });
+        let padding = width.saturating_sub(magnitude.len());
+        let mut value = prefix;
+        if negative {
+            value.push('-');
+        }
+        value.extend(std::iter::repeat_n(alphabet[0], padding));
+        value.extend(magnitude.iter().map(|&digit| alphabet[digit as usize]));
+        value.extend(&chars[end..]);
+        self.value = value;
+        Ok(self.value.clone())
+    }
+
+    /// Applies `min`, `max` and `on_bound` to a stepped value.
+    fn bound(&self, name: &str, number: Number, base: u32) -> Result<Number, Error> {
+        let (below, above) = match number {
+            Number::Small(value) => (self.min.is_some_and(|min| value < min), self.max.is_some_and(|max| value > max)),
+            // Too large for an i128, so past any bound on its side of zero.
+            Number::Big { negative, .. } => (negative && self.min.is_some(), !negative && self.max.is_some()),
+        };
+        if !below && !above {
+            return Ok(number);
+        }
+        let out_of_range = || Error::CounterOutOfRange(name.to_string());
+        match self.on_bound {
+            OnBound::Stop => Err(out_of_range()),
+            OnBound::Clamp => Ok(Number::Small(if below { self.min.unwrap() } else { self.max.unwrap() })),
+            OnBound::Wrap => {
+                // Nothing to wrap around to below an open-ended range.
+                let max = self.max.ok_or_else(out_of_range)?;
+                let min = self.min.unwrap_or(0);
+                let span = max.abs_diff(min).checked_add(1).ok_or_else(out_of_range)?;
+                let offset = number.rem_euclid(span, base).ok_or_else(out_of_range)?;
+                let min_offset = Number::Small(min).rem_euclid(span, base).unwrap();
+                let offset = if offset >= min_offset { offset - min_offset } else { offset + (span - min_offset) };
+                // The result lies in min..=max, so the wrapping add is exact.
+                Ok(Number::Small((min as u128).wrapping_add(offset) as i128))
+            }
+        }
+    }
+}
+
+/// A counter value: an `i128` when it fits, otherwise the digits of its
+/// magnitude in the counter's base, most significant first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Number {
+    Small(i128),
+    Big { negative: bool, digits: Vec<u32> },
+}
+
+impl Number {
+    fn parse(negative: bool, digits: &[u32], base: u32) -> Number {
+        let first = digits.iter().position(|&digit| digit != 0).unwrap_or(digits.len());
+        let digits = &digits[first..];
+        let value = digits.iter().try_fold(0i128, |value, &digit| value.checked_mul(base as i128)?.checked_add(digit as i128));
+        match value {
+            Some(value) => Number::Small(if negative { -value } else { value }),
+            None => Number::Big { negative, digits: digits.to_vec() },
+        }
+    }
+
+    /// The sign and the digits of the magnitude, at least one.
+    fn into_digits(self, base: u32) -> (bool, Vec<u32>) {
+        match self {
+            Number::Small(value) => {
+                let mut magnitude = value.unsigned_abs();
+                let mut digits = Vec::new();
+                loop {
+                    digits.push((magnitude % base as u128) as u32);
+                    magnitude /= base as u128;
+                    if magnitude == 0 {
+                        break;
+                    }
+                }
+                digits.reverse();
+                (value < 0, digits)
+            }
+            Number::Big { negative, digits } => (negative, digits),
+        }
+    }
+
+    fn add(self, delta: i128, base: u32) -> Number {
+        if let Number::Small(value) = self {
+            if let Some(value) = value.checked_add(delta) {
+                return Number::Small(value);
+            }
+        }
+        // The magnitude is past the i128 range and the step is at most a u64,
+        // so subtracting never crosses zero.
+        let (negative, digits) = self.into_digits(base);
+        let digits = if negative == (delta < 0) {
+            add_digits(&digits, delta.unsigned_abs(), base)
+        } else {
+            sub_digits(&digits, delta.unsigned_abs(), base)
+        };
+        Number::parse(negative, &digits, base)
+    }
+
+    /// The value modulo `span`, or `None` if the arithmetic would overflow.
+    fn rem_euclid(&self, span: u128, base: u32) -> Option<u128> {
+        let (negative, rem) = match self {
+            Number::Small(value) => (*value < 0, value.unsigned_abs() % span),
+            Number::Big { negative, digits } => {
+                let mut rem: u128 = 0;
+                for &digit in digits {
+                    rem = rem.checked_mul(base as u128)?.checked_add(digit as u128)? % span;
+                }
+                (*negative, rem)
+            }
+        };
+        Some(if negative && rem > 0 { span - rem } else { rem })
+    }
+}
+
+/// Adds `n` to the digits of a number in `base`.
+fn add_digits(digits: &[u32], mut n: u128, base: u32) -> Vec<u32> {
+    let base = base as u128;
+    let mut result = Vec::with_capacity(digits.len() + 1);
+    for &digit in digits.iter().rev() {
+        let sum = digit as u128 + n % base;
+        n = n / base + sum / base;
+        result.push((sum % base) as u32);
+    }
+    while n > 0 {
+        result.push((n % base) as u32);
+        n /= base;
+    }
+    result.reverse();
+    result
+}
+
+/// Subtracts `n` from the digits of a number in `base` that is at least `n`.
+fn sub_digits(digits: &[u32], mut n: u128, base: u32) -> Vec<u32> {
+    let base = base as u128;
+    let mut result = Vec::with_capacity(digits.len());
+    let mut borrow = 0;
+    for &digit in digits.iter().rev() {
+        let subtrahend = n % base + borrow;
+        n /= base;
+        borrow = (subtrahend > digit as u128) as u128;
+        result.push((digit as u128 + borrow * base - subtrahend) as u32);
+    }
+    result.reverse();
+    result
+}
+
+/// Whether `name` is a valid counter, array or macro name: a letter or
+/// underscore followed by letters, digits and underscores. The leading letter
+/// keeps counter names apart from the `{:width}` specifier.
+pub fn is_valid_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_alphabetic() || c == '_') && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
@@ -0,0 +1,170 @@
+// Coverage goals: the alternation branches, repeat bounds and class range
+// endpoints of a pattern. While a coverage walk is active, `generate` steers
+// each choice towards goals no earlier string has reached yet.
+
+use crate::parser::{self, Node};
+use crate::regex_generator::UNBOUNDED_REPEAT_SPAN;
+use crate::strategy;
+use std::collections::{HashMap, HashSet};
+
+/// A string from [`RegexGenerator::coverage_set`](crate::RegexGenerator::coverage_set)
+/// and the parts of the pattern it exercises.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coverage {
+    pub value: String,
+    /// One entry per goal, such as `(GET|POST) -> POST`, `[a-z]{2,5} -> count 5`
+    /// or `[a-z] -> 'z'`.
+    pub covers: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Goal {
+    Branch(usize),
+    Count(usize),
+    Char(char),
+}
+
+/// Goals are keyed by node id.
+pub(crate) type GoalKey = (usize, Goal);
+
+/// The counts a repeat must be seen with: its minimum, its maximum and one
+/// in between.
+pub(crate) fn repeat_bounds(min: usize, max: Option<usize>) -> Vec<usize> {
+    let max = max.unwrap_or(min + UNBOUNDED_REPEAT_SPAN);
+    let mut bounds = vec![min];
+    if max >= min + 2 {
+        bounds.push((min + max) / 2);
+    }
+    if max > min {
+        bounds.push(max);
+    }
+    bounds
+}
+
+fn collect_goals(node: &Node, is_root: bool, goals: &mut Vec<(GoalKey, String)>) {
+    let id = node.id();
+    match node {
+        Node::Group { alternatives, weights, .. } => {
+            if alternatives.len() > 1 {
+                let mut text = String::new();
+                if is_root {
+                    parser::write_alternatives(&mut text, alternatives, weights.as_deref()).unwrap();
+                } else {
+                    text = node.to_string();
+                }
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    let mut branch = String::new();
+                    parser::write_alternatives(&mut branch, std::slice::from_ref(alternative), None).unwrap();
+                    if branch.is_empty() {
+                        branch.push_str("(empty)");
+                    }
+                    goals.push(((id, Goal::Branch(i)), format!("{} -> {}", text, branch)));
+                }
+            }
+            for node in alternatives.iter().flatten() {
+                collect_goals(node, false, goals);
+            }
+        }
+        Node::Repeat { node: inner, min, max } => {
+            for count in repeat_bounds(*min, *max) {
+                goals.push(((id, Goal::Count(count)), format!("{} -> count {}", node, count)));
+            }
+            if *max != Some(0) {
+                collect_goals(inner, false, goals);
+            }
+        }
+        Node::Class(set) => {
+            for ch in strategy::class_endpoints(set) {
+                goals.push(((id, Goal::Char(ch)), format!("{} -> {:?}", node, ch)));
+            }
+        }
+        _ => {}
+    }
+}
+
+pub(crate) struct CoverageWalk {
+    descriptions: HashMap<GoalKey, String>,
+    uncovered: HashSet<GoalKey>,
+    hits: Vec<GoalKey>, // goals reached by the current string, in order
+    new_hits: usize,
+}
+
+impl CoverageWalk {
+    pub(crate) fn new(root: &Node) -> Self {
+        let mut goals = Vec::new();
+        collect_goals(root, true, &mut goals);
+        Self {
+            uncovered: goals.iter().map(|(key, _)| *key).collect(),
+            descriptions: goals.into_iter().collect(),
+            hits: Vec::new(),
+            new_hits: 0,
+        }
+    }
+
+    pub(crate) fn is_complete(&self) -> bool {
+        self.uncovered.is_empty()
+    }
+
+    /// Ends the current string, returning whether it reached a new goal and
+    /// the descriptions of every goal it reached.
+    pub(crate) fn finish_string(&mut self) -> (bool, Vec<String>) {
+        let covers = self.hits.drain(..).map(|key| self.descriptions[&key].clone()).collect();
+        let reached_new = self.new_hits > 0;
+        self.new_hits = 0;
+        (reached_new, covers)
+    }
+
+    pub(crate) fn hit(&mut self, node: &Node, goal: Goal) {
+        let key = (node.id(), goal);
+        if !self.descriptions.contains_key(&key) || self.hits.contains(&key) {
+            return;
+        }
+        if self.uncovered.remove(&key) {
+            self.new_hits += 1;
+        }
+        self.hits.push(key);
+    }
+
+    fn is_uncovered(&self, node: &Node, goal: Goal) -> bool {
+        self.uncovered.contains(&(node.id(), goal))
+    }
+
+    /// Number of uncovered goals in the subtree of `node`.
+    fn pending(&self, node: &Node) -> usize {
+        let id = node.id();
+        let own = self.uncovered.iter().filter(|(node, _)| *node == id).count();
+        own + match node {
+            Node::Group { alternatives, .. } => alternatives.iter().flatten().map(|node| self.pending(node)).sum(),
+            Node::Repeat { node, .. } => self.pending(node),
+            _ => 0,
+        }
+    }
+
+    /// The branch that reaches the most uncovered goals, if any does.
+    pub(crate) fn choose_branch(&self, node: &Node, alternatives: &[Vec<Node>]) -> Option<usize> {
+        let scores = alternatives.iter().enumerate().map(|(i, alternative)| {
+            let own = self.is_uncovered(node, Goal::Branch(i)) as usize;
+            own + alternative.iter().map(|node| self.pending(node)).sum::<usize>()
+        });
+        let (best, score) = scores.enumerate().fold((0, 0), |best, (i, score)| if score > best.1 { (i, score) } else { best });
+        (score > 0).then_some(best)
+    }
+
+    /// An uncovered bound of the repeat, or its maximum when only the
+    /// repeated node still has goals left.
+    pub(crate) fn choose_count(&self, node: &Node, inner: &Node, min: usize, max: Option<usize>) -> Option<usize> {
+        let bounds = repeat_bounds(min, max);
+        let uncovered: Vec<usize> = bounds.iter().copied().filter(|&count| self.is_uncovered(node, Goal::Count(count))).collect();
+        let inner_pending = self.pending(inner) > 0;
+        match uncovered.last() {
+            Some(&largest) if inner_pending => Some(largest),
+            Some(_) => uncovered.first().copied(),
+            None if inner_pending => bounds.last().copied(),
+            None => None,
+        }
+    }
+
+    pub(crate) fn choose_char(&self, node: &Node, set: &[char]) -> Option<char> {
+        strategy::class_endpoints(set).into_iter().find(|&ch| self.is_uncovered(node, Goal::Char(ch)))
+    }
+}
@@ -0,0 +1,43 @@
+// Word lists bundled with the `dictionaries` feature, which `\a{@name}`
+// draws from without any array being set.
+
+use crate::parser::Node;
+use crate::regex_generator::Arrays;
+
+#[cfg(feature = "dictionaries")]
+const DICTIONARIES: &[(&str, &str)] = &[
+    ("city", include_str!("../dictionaries/city.txt")),
+    ("company_suffix", include_str!("../dictionaries/company_suffix.txt")),
+    ("country", include_str!("../dictionaries/country.txt")),
+    ("first_name", include_str!("../dictionaries/first_name.txt")),
+    ("last_name", include_str!("../dictionaries/last_name.txt")),
+    ("street_suffix", include_str!("../dictionaries/street_suffix.txt")),
+    ("word", include_str!("../dictionaries/word.txt")),
+];
+
+#[cfg(not(feature = "dictionaries"))]
+const DICTIONARIES: &[(&str, &str)] = &[];
+
+/// The values of the bundled dictionary `name`, such as `first_name` or
+/// `city`. Returns `None` for an unknown name, and always without the
+/// `dictionaries` feature.
+pub fn dictionary(name: &str) -> Option<Vec<String>> {
+    let (_, text) = DICTIONARIES.iter().find(|(key, _)| *key == name)?;
+    Some(text.lines().map(str::to_string).collect())
+}
+
+/// The names of the bundled dictionaries.
+pub fn dictionary_names() -> impl Iterator<Item = &'static str> {
+    DICTIONARIES.iter().map(|(name, _)| *name)
+}
+
+/// Adds the dictionaries `node` refers to as `\a{@name}` to `arrays`.
+pub(crate) fn load(node: &Node, arrays: &mut Arrays) {
+    node.for_each_array(&mut |name| {
+        if !arrays.contains_key(name) {
+            if let Some(values) = name.strip_prefix('@').and_then(dictionary) {
+                arrays.insert(name.to_string(), values);
+            }
+        }
+    });
+}
@@ -0,0 +1,62 @@
+use crate::parser::{ArrayOrder, Node};
+use crate::regex_generator::{Arrays, UNBOUNDED_REPEAT_SPAN};
+use rand::distributions::{Distribution as _, WeightedIndex};
+use rand::Rng;
+
+/// How `generate` weighs the choices a pattern offers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Distribution {
+    /// Each alternation branch, repeat count and character is picked
+    /// uniformly on its own. Short branches and counts are favoured, since
+    /// they stand for fewer strings.
+    #[default]
+    PerNode,
+    /// Each string of the pattern is equally likely. A finite pattern that
+    /// [`enumerate`](crate::RegexGenerator::enumerate) accepts is sampled
+    /// exactly, by drawing a random index into its strings. Other patterns
+    /// only approximate it: branches and repeat counts are weighted by how
+    /// many ways they can be generated, so a string that can be built in
+    /// several ways comes up more often, and `*`, `+` and `{n,}` are limited
+    /// to the same span `generate` always uses.
+    UniformLanguage,
+}
+
+/// Natural log of the number of ways `node` can be generated. Logs keep
+/// patterns such as `\w{1,200}` from overflowing.
+pub(crate) fn log_weight(node: &Node, arrays: &Arrays) -> f64 {
+    match node {
+        Node::Literal(_) | Node::Backreference(_) | Node::Increment { .. } | Node::Current { .. } => 0.0,
+        Node::Class(set) => (set.len().max(1) as f64).ln(),
+        Node::Group { alternatives, .. } => {
+            log_sum(alternatives.iter().map(|alternative| sequence_log_weight(alternative, arrays)))
+        }
+        Node::Repeat { node, min, max } => {
+            let node_weight = log_weight(node, arrays);
+            let max = max.unwrap_or(min + UNBOUNDED_REPEAT_SPAN);
+            log_sum((*min..=max).map(|count| count as f64 * node_weight))
+        }
+        Node::PaddedNumber { digits, .. } => 9f64.ln() + (*digits as f64 - 1.0) * 10f64.ln(),
+        Node::Timestamp(spec) => (spec.span_seconds() as f64 + 1.0).ln(),
+        Node::Number(spec) => (spec.len() as f64).ln(),
+        Node::Identifier(spec) => spec.random_bits() as f64 * 2f64.ln(),
+        Node::Array { name, order: ArrayOrder::Random, .. } => (arrays.get(name).map_or(0, Vec::len).max(1) as f64).ln(),
+        Node::Array { .. } => 0.0,
+    }
+}
+
+pub(crate) fn sequence_log_weight(nodes: &[Node], arrays: &Arrays) -> f64 {
+    nodes.iter().map(|node| log_weight(node, arrays)).sum()
+}
+
+/// Picks an index with probability proportional to `exp(log_weights[i])`.
+pub(crate) fn pick<R: Rng>(log_weights: &[f64], rng: &mut R) -> usize {
+    let max = log_weights.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let weights = log_weights.iter().map(|weight| (weight - max).exp());
+    WeightedIndex::new(weights).map(|index| index.sample(rng)).unwrap_or(0)
+}
+
+fn log_sum(log_weights: impl Iterator<Item = f64>) -> f64 {
+    let log_weights: Vec<f64> = log_weights.collect();
+    let max = log_weights.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    max + log_weights.iter().map(|weight| (weight - max).exp()).sum::<f64>().ln()
+}
@@ -0,0 +1,51 @@
+use std::fmt;
+
+/// Errors returned by the generator APIs that can fail.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The pattern contains a token that has no fixed language, such as a
+    /// backreference or an incrementing value.
+    UnsupportedToken(String),
+    /// The operation needs a finite language but the pattern is unbounded.
+    InfiniteLanguage,
+    /// Every string of the pattern has already been generated.
+    Exhausted,
+    /// A counter spec such as `order=1000,desc` could not be parsed.
+    InvalidCounter(String),
+    /// The named counter (empty for a plain `\i`) would step past its
+    /// `min` or `max` and is set to stop there.
+    CounterOutOfRange(String),
+    /// Array values given inline or in a file could not be read.
+    InvalidArray(String),
+    /// Entries of the named pattern array kept expanding arrays past the
+    /// depth limit, as when an entry refers to its own array.
+    RecursionLimit(String),
+    /// A macro library could not be read.
+    InvalidMacro(String),
+    /// The named `\u` identifier kind has no value left above the last one
+    /// it made.
+    IdentifierOverflow(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnsupportedToken(token) => {
+                write!(f, "pattern token {} cannot be enumerated", token)
+            }
+            Error::InfiniteLanguage => {
+                write!(f, "pattern matches infinitely many strings")
+            }
+            Error::Exhausted => write!(f, "every string of the pattern has already been generated"),
+            Error::InvalidCounter(spec) => write!(f, "invalid counter spec '{}'", spec),
+            Error::CounterOutOfRange(name) if name.is_empty() => write!(f, "counter \\i reached its bound"),
+            Error::CounterOutOfRange(name) => write!(f, "counter \\i{{{}}} reached its bound", name),
+            Error::InvalidArray(message) => write!(f, "invalid array values: {}", message),
+            Error::InvalidMacro(message) => write!(f, "invalid macro library: {}", message),
+            Error::RecursionLimit(name) => write!(f, "pattern array \\a{{{}}} nests too deeply", name),
+            Error::IdentifierOverflow(kind) => write!(f, "\\u{{{}}} has no identifier left above the last one", kind),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,185 @@
+// The `\u{...}` token: identifiers in the formats of UUID versions 4 and 7,
+// ULID, Nano ID and Snowflake IDs.
+
+use crate::timestamp;
+use std::fmt;
+
+/// Characters of a Nano ID, in the order of the reference implementation.
+const NANOID_ALPHABET: &[u8; 64] = b"useandom-26T198340PX75pxJACKVERYMINDBUSHWOLF_GQZbfghjklqvwyzrict";
+const NANOID_LENGTH: usize = 21;
+/// Crockford's base 32, which leaves out I, L, O and U.
+const CROCKFORD: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+/// Snowflake times count milliseconds from 2010-11-04T01:42:54.657Z.
+const SNOWFLAKE_EPOCH: i64 = 1_288_834_974_657;
+const SNOWFLAKE_SEQUENCE_BITS: u32 = 12;
+const SNOWFLAKE_WORKER_BITS: u32 = 10;
+
+/// The time `\u{uuid7}`, `\u{ulid}` and `\u{snowflake}` put in their
+/// identifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Clock {
+    /// The current system time (the default).
+    #[default]
+    System,
+    /// A fixed time in milliseconds since the Unix epoch, so that a seeded
+    /// generator repeats its identifiers.
+    Fixed(i64),
+}
+
+impl Clock {
+    /// Parses `system`, a number of milliseconds since the Unix epoch, or a
+    /// date or time such as `2024-01-01T12:00:00Z`, which is UTC without an
+    /// offset.
+    pub fn parse(text: &str) -> Option<Clock> {
+        let text = text.trim();
+        if text == "system" {
+            return Some(Clock::System);
+        }
+        if let Ok(millis) = text.parse() {
+            return Some(Clock::Fixed(millis));
+        }
+        timestamp::parse_time(text).map(|time| Clock::Fixed(time.timestamp_millis()))
+    }
+
+    /// Milliseconds since the Unix epoch.
+    pub(crate) fn now(&self) -> i64 {
+        match self {
+            Clock::System => system_millis(),
+            Clock::Fixed(millis) => *millis,
+        }
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+fn system_millis() -> i64 {
+    js_sys::Date::now() as i64
+}
+
+#[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+fn system_millis() -> i64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_millis() as i64)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum IdSpec {
+    Uuid4,
+    Uuid7,
+    Ulid,
+    /// A Nano ID of the given length.
+    NanoId(usize),
+    /// A Snowflake ID from the given worker.
+    Snowflake(u16),
+}
+
+impl IdSpec {
+    /// Parses `uuid4`, `uuid7`, `ulid`, `nanoid[:LENGTH]` or
+    /// `snowflake[:WORKER]`, with a worker from 0 to 1023.
+    pub(crate) fn parse(source: &str) -> Option<IdSpec> {
+        let (kind, argument) = match source.split_once(':') {
+            Some((kind, argument)) => (kind.trim(), Some(argument.trim())),
+            None => (source.trim(), None),
+        };
+        match (kind, argument) {
+            ("uuid4", None) => Some(IdSpec::Uuid4),
+            ("uuid7", None) => Some(IdSpec::Uuid7),
+            ("ulid", None) => Some(IdSpec::Ulid),
+            ("nanoid", None) => Some(IdSpec::NanoId(NANOID_LENGTH)),
+            ("nanoid", Some(length)) => length.parse().ok().filter(|&length| length > 0).map(IdSpec::NanoId),
+            ("snowflake", None) => Some(IdSpec::Snowflake(0)),
+            ("snowflake", Some(worker)) => {
+                worker.parse().ok().filter(|&worker| worker < 1 << SNOWFLAKE_WORKER_BITS).map(IdSpec::Snowflake)
+            }
+            _ => None,
+        }
+    }
+
+    /// Number of random bits in one identifier.
+    pub(crate) fn random_bits(&self) -> usize {
+        match self {
+            IdSpec::Uuid4 => 122,
+            IdSpec::Uuid7 => 74,
+            IdSpec::Ulid => 80,
+            IdSpec::NanoId(length) => 6 * length,
+            IdSpec::Snowflake(_) => 0,
+        }
+    }
+}
+
+impl fmt::Display for IdSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IdSpec::Uuid4 => write!(f, "uuid4"),
+            IdSpec::Uuid7 => write!(f, "uuid7"),
+            IdSpec::Ulid => write!(f, "ulid"),
+            IdSpec::NanoId(length) => write!(f, "nanoid:{}", length),
+            IdSpec::Snowflake(worker) => write!(f, "snowflake:{}", worker),
+        }
+    }
+}
+
+/// A version 4 UUID from 128 random bits.
+pub(crate) fn uuid4(random: u128) -> String {
+    hyphenate(with_version(random, 4))
+}
+
+/// A version 7 UUID: 48 bits of milliseconds, then random bits.
+pub(crate) fn uuid7(millis: i64, random: u128) -> String {
+    let time = (millis.max(0) as u128 & ((1 << 48) - 1)) << 80;
+    hyphenate(with_version(time | (random & ((1 << 80) - 1)), 7))
+}
+
+fn with_version(bits: u128, version: u128) -> u128 {
+    let bits = (bits & !(0xf << 76)) | (version << 76);
+    (bits & !(0b11 << 62)) | (0b10 << 62)
+}
+
+fn hyphenate(bits: u128) -> String {
+    let hex = format!("{:032x}", bits);
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+/// The next ULID after `last`: 48 bits of milliseconds and 80 random bits.
+/// Within one millisecond, or when the clock goes back, it is `last` plus
+/// one, so the identifiers always increase; `None` when `last` is the
+/// largest ULID.
+pub(crate) fn next_ulid(last: Option<u128>, millis: i64, random: u128) -> Option<u128> {
+    let time = millis.max(0) as u128 & ((1 << 48) - 1);
+    match last {
+        Some(last) if last >> 80 >= time => last.checked_add(1),
+        _ => Some((time << 80) | (random & ((1 << 80) - 1))),
+    }
+}
+
+/// A ULID in 26 characters of Crockford's base 32.
+pub(crate) fn ulid(bits: u128) -> String {
+    (0..26).rev().map(|i| CROCKFORD[(bits >> (5 * i)) as usize & 31] as char).collect()
+}
+
+/// The next Snowflake ID after `last`: 41 bits of milliseconds since the
+/// Snowflake epoch, the worker in 10 bits and a 12-bit sequence. Within one
+/// millisecond, or when the clock goes back, the sequence counts up from
+/// the time of `last`, moving on a millisecond when it runs out or the ID
+/// would not exceed `last`. `None` when the 41 bits of time run out.
+pub(crate) fn next_snowflake(last: Option<u64>, millis: i64, worker: u16) -> Option<u64> {
+    let shift = SNOWFLAKE_SEQUENCE_BITS + SNOWFLAKE_WORKER_BITS;
+    let id = |time: u64, sequence: u64| (time << shift) | (worker as u64) << SNOWFLAKE_SEQUENCE_BITS | sequence;
+    let time = millis.saturating_sub(SNOWFLAKE_EPOCH).max(0) as u64 & ((1 << 41) - 1);
+    let Some(last) = last else {
+        return Some(id(time, 0));
+    };
+    let (last_time, last_sequence) = (last >> shift, last & ((1 << SNOWFLAKE_SEQUENCE_BITS) - 1));
+    if time > last_time {
+        return Some(id(time, 0));
+    }
+    match last_sequence + 1 < 1 << SNOWFLAKE_SEQUENCE_BITS {
+        true if id(last_time, last_sequence + 1) > last => Some(id(last_time, last_sequence + 1)),
+        _ if last_time + 1 < 1 << 41 => Some(id(last_time + 1, 0)),
+        _ => None,
+    }
+}
+
+/// A Nano ID from one random byte per character.
+pub(crate) fn nanoid(random: impl Iterator<Item = u8>) -> String {
+    random.map(|byte| NANOID_ALPHABET[byte as usize & 63] as char).collect()
+}
@@ -0,0 +1,566 @@
+// The set of strings a pattern can produce, compiled into a DFA so it can
+// be listed, counted and indexed without duplicates. Tokens
+// whose output depends on generator state (`\i`, backreferences) have no
+// fixed language and are rejected at compile time.
+
+use crate::error::Error;
+use crate::parser::Node;
+use crate::regex_generator::Arrays;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+/// Order in which [`RegexGenerator::enumerate`](crate::RegexGenerator::enumerate) lists strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Order {
+    /// Shorter strings first, strings of equal length in lexicographic order.
+    /// Works for infinite patterns.
+    #[default]
+    Shortlex,
+    /// Plain lexicographic (dictionary) order. Needs a finite language or a
+    /// maximum length.
+    Lexicographic,
+}
+
+struct State {
+    accepting: bool,
+    transitions: Vec<(char, usize)>, // sorted by char
+}
+
+/// Longest string `nth` will build before giving up on an index, so a huge
+/// index into a slowly growing pattern such as `a*` cannot run away.
+const MAX_INDEXED_LEN: usize = 1 << 16;
+
+/// Lengths per block of cached counts.
+const COUNT_BLOCK: usize = 64;
+/// Blocks of counts kept at a time besides the checkpoints.
+const COUNT_BLOCKS: usize = 4;
+
+pub(crate) struct Language {
+    states: Vec<State>, // state 0 is the start state; empty for an empty language
+    min_dist: Vec<usize>,
+    max_len: Option<usize>, // None for infinite languages
+    counts: RefCell<Counts>,
+}
+
+impl Language {
+    pub(crate) fn compile(root: &Node, arrays: &Arrays) -> Result<Self, Error> {
+        let mut nfa = Nfa::default();
+        let start = nfa.add_state();
+        let accept = nfa.build(root, start, arrays)?;
+        Ok(nfa.into_dfa(start, accept))
+    }
+
+    /// Number of distinct strings, or `None` if infinite or above `u128::MAX`.
+    pub(crate) fn cardinality(&self) -> Option<u128> {
+        let max_len = self.max_len?;
+        (0..=max_len).try_fold(0u128, |total, len| total.checked_add(self.exact_count(0, len)?))
+    }
+
+    pub(crate) fn is_infinite(&self) -> bool {
+        self.max_len.is_none()
+    }
+
+    /// The string at `index` in shortlex order. Like `index_of`, it only
+    /// steps over counts that fit in a `u128` and gives `None` when it would
+    /// have to step over a saturated one.
+    pub(crate) fn nth(&self, mut index: u128) -> Option<String> {
+        if self.states.is_empty() {
+            return None;
+        }
+        let max_len = self.max_len.unwrap_or(MAX_INDEXED_LEN);
+        let mut len = 0;
+        loop {
+            if index < self.count(0, len) {
+                break;
+            }
+            index -= self.exact_count(0, len)?;
+            len += 1;
+            if len > max_len {
+                return None;
+            }
+        }
+
+        let mut result = String::with_capacity(len);
+        let mut state = 0;
+        for remaining in (0..len).rev() {
+            for &(ch, target) in &self.states[state].transitions {
+                if index < self.count(target, remaining) {
+                    result.push(ch);
+                    state = target;
+                    break;
+                }
+                index -= self.exact_count(target, remaining)?;
+            }
+        }
+        Some(result)
+    }
+
+    /// The position of `value` in shortlex order, if the pattern produces it.
+    pub(crate) fn index_of(&self, value: &str) -> Option<u128> {
+        if self.states.is_empty() {
+            return None;
+        }
+        let chars: Vec<char> = value.chars().collect();
+        let mut index = (0..chars.len()).try_fold(0u128, |total, len| total.checked_add(self.exact_count(0, len)?))?;
+        let mut state = 0;
+        for (position, &ch) in chars.iter().enumerate() {
+            let remaining = chars.len() - position - 1;
+            let mut next = None;
+            for &(label, target) in &self.states[state].transitions {
+                if label == ch {
+                    next = Some(target);
+                    break;
+                }
+                index = index.checked_add(self.exact_count(target, remaining)?)?;
+            }
+            state = next?;
+        }
+        if self.states[state].accepting {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn iter(&self, order: Order, max_len: Option<usize>) -> Result<Enumerate<'_>, Error> {
+        let limit = match (self.max_len, max_len) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        if order == Order::Lexicographic && limit.is_none() {
+            return Err(Error::InfiniteLanguage);
+        }
+        Ok(Enumerate {
+            language: self,
+            order,
+            limit,
+            len: 0,
+            stack: Vec::new(),
+            prefix: Vec::new(),
+            started: false,
+        })
+    }
+
+    /// Number of strings of exactly `len` characters accepted from `state`.
+    fn count(&self, state: usize, len: usize) -> u128 {
+        if self.states.is_empty() {
+            return 0;
+        }
+        self.counts.borrow_mut().get(&self.states, state, len)
+    }
+
+    /// Like `count`, but `None` once the count no longer fits in a `u128`.
+    fn exact_count(&self, state: usize, len: usize) -> Option<u128> {
+        Some(self.count(state, len)).filter(|&count| count != u128::MAX)
+    }
+}
+
+/// The counts of one length: `(state, count)` for the states with at least
+/// one string of that length, sorted by state. Counts saturate at
+/// `u128::MAX`.
+type Row = Vec<(usize, u128)>;
+
+/// Cache of how many strings of each length every state accepts. Only the
+/// rows at multiples of `COUNT_BLOCK` are kept for good; the rows between
+/// them are rebuilt a block at a time, and only the `COUNT_BLOCKS` blocks
+/// used last are kept, so memory does not grow with the square of the
+/// pattern.
+#[derive(Default)]
+struct Counts {
+    predecessors: Vec<Vec<usize>>, // The source of every transition into each state
+    checkpoints: Vec<Row>, // Rows for lengths 0, COUNT_BLOCK, 2 * COUNT_BLOCK...
+    blocks: VecDeque<(usize, Vec<Row>)>, // Recently used blocks by number, the latest last
+}
+
+impl Counts {
+    fn get(&mut self, states: &[State], state: usize, len: usize) -> u128 {
+        let block = len / COUNT_BLOCK;
+        let rows = match self.blocks.iter().position(|&(number, _)| number == block) {
+            Some(position) => self.blocks.remove(position).unwrap(),
+            None => (block, self.build_block(states, block)),
+        };
+        if self.blocks.len() == COUNT_BLOCKS {
+            self.blocks.pop_front();
+        }
+        self.blocks.push_back(rows);
+        let row = &self.blocks.back().unwrap().1[len % COUNT_BLOCK];
+        row.binary_search_by_key(&state, |&(state, _)| state).map_or(0, |i| row[i].1)
+    }
+
+    fn build_block(&mut self, states: &[State], block: usize) -> Vec<Row> {
+        if self.checkpoints.is_empty() {
+            self.predecessors = vec![Vec::new(); states.len()];
+            for (source, state) in states.iter().enumerate() {
+                for &(_, target) in &state.transitions {
+                    self.predecessors[target].push(source);
+                }
+            }
+            let accepting = states.iter().enumerate().filter(|(_, state)| state.accepting);
+            self.checkpoints.push(accepting.map(|(state, _)| (state, 1)).collect());
+        }
+        while self.checkpoints.len() <= block {
+            let mut row = self.checkpoints.last().unwrap().clone();
+            for _ in 0..COUNT_BLOCK {
+                row = self.step(&row);
+            }
+            self.checkpoints.push(row);
+        }
+        let mut rows = vec![self.checkpoints[block].clone()];
+        for _ in 1..COUNT_BLOCK {
+            rows.push(self.step(rows.last().unwrap()));
+        }
+        rows
+    }
+
+    /// The row one character longer than `row`.
+    fn step(&self, row: &Row) -> Row {
+        let mut counts: BTreeMap<usize, u128> = BTreeMap::new();
+        for &(target, count) in row {
+            for &source in &self.predecessors[target] {
+                let sum = counts.entry(source).or_insert(0);
+                *sum = sum.saturating_add(count);
+            }
+        }
+        counts.into_iter().collect()
+    }
+}
+
+/// Iterator over every string a pattern can produce, created by
+/// [`RegexGenerator::enumerate`](crate::RegexGenerator::enumerate).
+pub struct Enumerate<'a> {
+    language: &'a Language,
+    order: Order,
+    limit: Option<usize>,
+    len: usize,
+    stack: Vec<(usize, usize)>, // (state, next transition to try)
+    prefix: Vec<char>,
+    started: bool,
+}
+
+impl Enumerate<'_> {
+    fn pop(&mut self) {
+        self.stack.pop();
+        if !self.stack.is_empty() {
+            self.prefix.pop();
+        }
+    }
+
+    fn next_shortlex(&mut self) -> Option<String> {
+        let language = self.language;
+        loop {
+            if self.stack.is_empty() {
+                if self.started {
+                    self.len += 1;
+                }
+                self.started = true;
+                if language.states.is_empty() || self.limit.is_some_and(|limit| self.len > limit) {
+                    return None;
+                }
+                if language.count(0, self.len) > 0 {
+                    self.stack.push((0, 0));
+                }
+                continue;
+            }
+
+            let remaining = self.len - (self.stack.len() - 1);
+            if remaining == 0 {
+                let result = self.prefix.iter().collect();
+                self.pop();
+                return Some(result);
+            }
+
+            let (state, next) = *self.stack.last().unwrap();
+            let transitions = &language.states[state].transitions;
+            match (next..transitions.len()).find(|&i| language.count(transitions[i].1, remaining - 1) > 0) {
+                Some(i) => {
+                    self.stack.last_mut().unwrap().1 = i + 1;
+                    self.prefix.push(transitions[i].0);
+                    self.stack.push((transitions[i].1, 0));
+                }
+                None => self.pop(),
+            }
+        }
+    }
+
+    fn next_lexicographic(&mut self) -> Option<String> {
+        let language = self.language;
+        let limit = self.limit.unwrap();
+        if self.stack.is_empty() {
+            if self.started || language.states.is_empty() || language.min_dist[0] > limit {
+                return None;
+            }
+            self.started = true;
+            self.stack.push((0, 0));
+            if language.states[0].accepting {
+                return Some(String::new());
+            }
+        }
+        loop {
+            let (state, next) = *self.stack.last()?;
+            let budget = limit - (self.stack.len() - 1);
+            let transitions = &language.states[state].transitions;
+            let found = if budget == 0 {
+                None
+            } else {
+                (next..transitions.len()).find(|&i| language.min_dist[transitions[i].1] < budget)
+            };
+            match found {
+                Some(i) => {
+                    let target = transitions[i].1;
+                    self.stack.last_mut().unwrap().1 = i + 1;
+                    self.prefix.push(transitions[i].0);
+                    self.stack.push((target, 0));
+                    if language.states[target].accepting {
+                        return Some(self.prefix.iter().collect());
+                    }
+                }
+                None => self.pop(),
+            }
+        }
+    }
+}
+
+impl Iterator for Enumerate<'_> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        match self.order {
+            Order::Shortlex => self.next_shortlex(),
+            Order::Lexicographic => self.next_lexicographic(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Nfa {
+    epsilon: Vec<Vec<usize>>,
+    transitions: Vec<Vec<(char, usize)>>,
+}
+
+impl Nfa {
+    fn add_state(&mut self) -> usize {
+        self.epsilon.push(Vec::new());
+        self.transitions.push(Vec::new());
+        self.epsilon.len() - 1
+    }
+
+    fn add_literal(&mut self, from: usize, text: &str) -> usize {
+        text.chars().fold(from, |state, ch| {
+            let next = self.add_state();
+            self.transitions[state].push((ch, next));
+            next
+        })
+    }
+
+    /// Adds the states for `node` starting at `from` and returns its end state.
+    fn build(&mut self, node: &Node, from: usize, arrays: &Arrays) -> Result<usize, Error> {
+        match node {
+            Node::Literal(ch) => Ok(self.add_literal(from, &ch.to_string())),
+            Node::Class(set) => {
+                let end = self.add_state();
+                self.transitions[from].extend(set.iter().map(|&ch| (ch, end)));
+                Ok(end)
+            }
+            Node::Group { alternatives, .. } => {
+                let end = self.add_state();
+                for alternative in alternatives {
+                    let mut state = from;
+                    for node in alternative {
+                        state = self.build(node, state, arrays)?;
+                    }
+                    self.epsilon[state].push(end);
+                }
+                Ok(end)
+            }
+            Node::Repeat { node, min, max } => {
+                let mut state = from;
+                for _ in 0..*min {
+                    state = self.build(node, state, arrays)?;
+                }
+                match max {
+                    Some(max) => {
+                        let end = self.add_state();
+                        self.epsilon[state].push(end);
+                        for _ in *min..*max {
+                            state = self.build(node, state, arrays)?;
+                            self.epsilon[state].push(end);
+                        }
+                        Ok(end)
+                    }
+                    None => {
+                        let loop_start = self.add_state();
+                        self.epsilon[state].push(loop_start);
+                        let body_end = self.build(node, loop_start, arrays)?;
+                        self.epsilon[body_end].push(loop_start);
+                        Ok(loop_start)
+                    }
+                }
+            }
+            Node::PaddedNumber { digits, width } => {
+                let mut state = self.add_literal(from, &"0".repeat(width.saturating_sub(*digits)));
+                let first_digit = Node::Class(('1'..='9').collect());
+                state = self.build(&first_digit, state, arrays)?;
+                let digit = Node::Class(('0'..='9').collect());
+                for _ in 1..*digits {
+                    state = self.build(&digit, state, arrays)?;
+                }
+                Ok(state)
+            }
+            // Table columns depend on each other, which the automaton cannot express.
+            Node::Array { name, .. } if name.contains('.') => Err(Error::UnsupportedToken(format!("\\a{{{}}}", name))),
+            Node::Array { name, .. } => {
+                let end = self.add_state();
+                match arrays.get(name) {
+                    Some(values) if !values.is_empty() => {
+                        for value in values {
+                            let state = self.add_literal(from, value);
+                            self.epsilon[state].push(end);
+                        }
+                    }
+                    _ => self.epsilon[from].push(end),
+                }
+                Ok(end)
+            }
+            Node::Backreference(index) => Err(Error::UnsupportedToken(format!("\\{}", index))),
+            Node::Increment { .. } => Err(Error::UnsupportedToken("\\i".to_string())),
+            Node::Current { .. } => Err(Error::UnsupportedToken("\\I".to_string())),
+            Node::Timestamp(_) => Err(Error::UnsupportedToken("\\T".to_string())),
+            Node::Number(_) => Err(Error::UnsupportedToken("\\r".to_string())),
+            Node::Identifier(_) => Err(Error::UnsupportedToken("\\u".to_string())),
+        }
+    }
+
+    fn closure(&self, states: impl IntoIterator<Item = usize>) -> Vec<usize> {
+        let mut seen: BTreeSet<usize> = BTreeSet::new();
+        let mut pending: Vec<usize> = states.into_iter().collect();
+        while let Some(state) = pending.pop() {
+            if seen.insert(state) {
+                pending.extend(&self.epsilon[state]);
+            }
+        }
+        seen.into_iter().collect()
+    }
+
+    fn into_dfa(self, start: usize, accept: usize) -> Language {
+        // Subset construction
+        let mut sets = vec![self.closure([start])];
+        let mut index: HashMap<Vec<usize>, usize> = HashMap::from([(sets[0].clone(), 0)]);
+        let mut states = Vec::new();
+        let mut i = 0;
+        while i < sets.len() {
+            let mut moves: BTreeMap<char, Vec<usize>> = BTreeMap::new();
+            for &state in &sets[i] {
+                for &(ch, target) in &self.transitions[state] {
+                    moves.entry(ch).or_default().push(target);
+                }
+            }
+            let mut transitions = Vec::new();
+            for (ch, targets) in moves {
+                let set = self.closure(targets);
+                let target = *index.entry(set.clone()).or_insert_with(|| {
+                    sets.push(set);
+                    sets.len() - 1
+                });
+                transitions.push((ch, target));
+            }
+            states.push(State { accepting: sets[i].contains(&accept), transitions });
+            i += 1;
+        }
+        trim(states)
+    }
+}
+
+/// Drops states that cannot reach an accepting state and works out the
+/// distance and length bounds the iterators rely on.
+fn trim(states: Vec<State>) -> Language {
+    let mut reverse: Vec<Vec<usize>> = vec![Vec::new(); states.len()];
+    for (from, state) in states.iter().enumerate() {
+        for &(_, to) in &state.transitions {
+            reverse[to].push(from);
+        }
+    }
+    let mut min_dist = vec![usize::MAX; states.len()];
+    let mut queue = VecDeque::new();
+    for (i, state) in states.iter().enumerate() {
+        if state.accepting {
+            min_dist[i] = 0;
+            queue.push_back(i);
+        }
+    }
+    while let Some(state) = queue.pop_front() {
+        for &from in &reverse[state] {
+            if min_dist[from] == usize::MAX {
+                min_dist[from] = min_dist[state] + 1;
+                queue.push_back(from);
+            }
+        }
+    }
+
+    let empty = Language { states: Vec::new(), min_dist: Vec::new(), max_len: Some(0), counts: RefCell::default() };
+    if min_dist.first().is_none_or(|&d| d == usize::MAX) {
+        return empty;
+    }
+
+    // Renumber the live states, keeping the start state at 0.
+    let mut new_index = vec![usize::MAX; states.len()];
+    let mut live = 0;
+    for (i, &dist) in min_dist.iter().enumerate() {
+        if dist != usize::MAX {
+            new_index[i] = live;
+            live += 1;
+        }
+    }
+    let mut trimmed = Vec::with_capacity(live);
+    let mut trimmed_dist = Vec::with_capacity(live);
+    for (state, dist) in states.into_iter().zip(min_dist) {
+        if dist != usize::MAX {
+            let transitions = state
+                .transitions
+                .into_iter()
+                .filter(|&(_, to)| new_index[to] != usize::MAX)
+                .map(|(ch, to)| (ch, new_index[to]))
+                .collect();
+            trimmed.push(State { accepting: state.accepting, transitions });
+            trimmed_dist.push(dist);
+        }
+    }
+
+    let max_len = longest_path(&trimmed);
+    Language { states: trimmed, min_dist: trimmed_dist, max_len, counts: RefCell::default() }
+}
+
+/// Length of the longest accepted string, or `None` if there is a cycle.
+fn longest_path(states: &[State]) -> Option<usize> {
+    // Iterative DFS in post order; `longest[s]` is the longest path to an
+    // accepting state from `s`.
+    const UNVISITED: u8 = 0;
+    const ACTIVE: u8 = 1;
+    const DONE: u8 = 2;
+    let mut mark = vec![UNVISITED; states.len()];
+    let mut longest = vec![0usize; states.len()];
+    let mut stack = vec![(0usize, 0usize)];
+    mark[0] = ACTIVE;
+    while let Some(&mut (state, ref mut next)) = stack.last_mut() {
+        if let Some(&(_, target)) = states[state].transitions.get(*next) {
+            *next += 1;
+            match mark[target] {
+                ACTIVE => return None,
+                UNVISITED => {
+                    mark[target] = ACTIVE;
+                    stack.push((target, 0));
+                }
+                _ => {}
+            }
+        } else {
+            longest[state] = states[state]
+                .transitions
+                .iter()
+                .map(|&(_, target)| longest[target] + 1)
+                .max()
+                .unwrap_or(0);
+            mark[state] = DONE;
+            stack.pop();
+        }
+    }
+    Some(longest[0])
+}
@@ -1,5 +1,33 @@
+mod alias;
+mod array_source;
+mod counter;
+mod coverage;
+mod dictionary;
+mod distribution;
+mod error;
+mod identifier;
+mod language;
+mod library;
+mod numeric;
+mod pairwise;
+mod parser;
+mod permutation;
 mod regex_generator;
+mod state;
+mod strategy;
+mod timestamp;
+pub use array_source::{parse_array_file, parse_array_list, parse_pattern_list, parse_table, parse_weighted_array_file, parse_weighted_array_list};
+pub use counter::{is_valid_name, Alphabet, Counter, CounterMode, OnBound};
+pub use coverage::Coverage;
+pub use dictionary::{dictionary, dictionary_names};
+pub use distribution::Distribution;
+pub use error::Error;
+pub use identifier::Clock;
+pub use language::{Enumerate, Order};
+pub use library::{parse_library, standard_library};
 pub use regex_generator::RegexGenerator;
+pub use state::GeneratorState;
+pub use strategy::Strategy;
 
 #[cfg(feature = "wasm")]
 mod wasm;
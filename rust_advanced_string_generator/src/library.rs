@@ -0,0 +1,53 @@
+// Named sub-patterns for `\m{name}`: the standard library shipped with the
+// crate and `name = pattern` library files.
+
+use crate::counter;
+use crate::error::Error;
+use std::collections::HashMap;
+
+/// Macro definitions keyed by name.
+pub(crate) type Macros = HashMap<String, String>;
+
+/// The standard library. Its groups are non-capturing so that using a
+/// macro does not shift the numbers of the pattern's own groups.
+const STANDARD: &[(&str, &str)] = &[
+    ("octet", r"(?:25[0-5]|2[0-4]\d|1\d\d|[1-9]?\d)"),
+    ("hex", r"[0-9a-f]"),
+    ("email_local", r"[a-z][a-z0-9]{1,11}(?:[._][a-z0-9]{1,8})?"),
+    ("email", r"\m{email_local}@[a-z][a-z0-9]{1,11}\.(?:com|org|net|io|dev)"),
+    ("uuid", r"\m{hex}{8}-\m{hex}{4}-4\m{hex}{3}-[89ab]\m{hex}{3}-\m{hex}{12}"),
+    ("ipv4", r"\m{octet}\.\m{octet}\.\m{octet}\.\m{octet}"),
+    ("ipv6", r"\m{hex}{1,4}(?::\m{hex}{1,4}){7}"),
+    ("mac", r"\m{hex}{2}(?::\m{hex}{2}){5}"),
+    ("hex_color", r"#(?:\m{hex}{3}){1,2}"),
+    ("semver", r"(?:0|[1-9]\d{0,2})\.(?:0|[1-9]\d{0,2})\.(?:0|[1-9]\d{0,2})(?:-(?:alpha|beta|rc)\.(?:0|[1-9]\d?))?"),
+    ("slug", r"[a-z0-9]{1,10}(?:-[a-z0-9]{1,10}){0,4}"),
+];
+
+/// The macros of the standard library as `(name, pattern)` pairs.
+pub fn standard_library() -> impl Iterator<Item = (&'static str, &'static str)> {
+    STANDARD.iter().copied()
+}
+
+pub(crate) fn standard_macros() -> Macros {
+    standard_library().map(|(name, pattern)| (name.to_string(), pattern.to_string())).collect()
+}
+
+/// Reads a library file of `name = pattern` lines. Empty lines and lines
+/// starting with `#` are skipped, and spaces around the name and pattern
+/// are trimmed.
+pub fn parse_library(text: &str) -> Result<Vec<(String, String)>, Error> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(number, line)| {
+            let invalid = |message: &str| Error::InvalidMacro(format!("line {}: {}", number + 1, message));
+            let (name, pattern) = line.split_once('=').ok_or_else(|| invalid("expected name = pattern"))?;
+            if !counter::is_valid_name(name.trim()) {
+                return Err(invalid(&format!("'{}' is not a valid name", name.trim())));
+            }
+            Ok((name.trim().to_string(), pattern.trim().to_string()))
+        })
+        .collect()
+}
@@ -1,8 +1,9 @@
+use advanced_string_generator::{is_valid_name, parse_array_file, parse_array_list, parse_pattern_list, parse_table, parse_weighted_array_file, parse_weighted_array_list, Clock, Counter, CounterMode, Distribution, GeneratorState, Order, RegexGenerator, Strategy};
 use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
 use std::process;
-
-mod regex_generator; // Assuming your main logic is in `regex_generator.rs`
-use regex_generator::RegexGenerator;
+use std::str::FromStr;
 
 fn print_help() {
     println!(
@@ -12,8 +13,58 @@ fn print_help() {
         -h, --help              Prints help information
         -v, --version           Prints version information
         -p, --pattern PATTERN   Specifies the pattern to use
-        -i, --increment VALUE   Initial value for the increment (optional)
-        -a, --array VALUE       Array of strings (comma-separated) for /a pattern (optional)
+        -i, --increment VALUE   Initial value for the increment (optional). Use
+                                NAME=START for a named counter and add ,asc ,desc
+                                or ,width=N to set its direction and padding,
+                                ,step=N ,min=N ,max=N for its range, ,stop
+                                ,wrap or ,clamp for what happens at a bound and
+                                ,alphabet=NAME to count in decimal, hex, HEX,
+                                base36, BASE36, base62, letters, LETTERS or
+                                custom digits; repeat the option for several
+                                counters
+        --counter-mode MODE     per-occurrence (default) advances a counter at every
+                                \\i; per-call advances it once per generated string
+        -a, --array VALUE       Array of strings (comma-separated) for /a pattern (optional).
+                                Use NAME=V1,V2 for the array of \\a｛NAME｝; repeat the
+                                option for several arrays. Quote values holding commas
+                                (\"a, b\") or escape them (a\\,b)
+        --weighted-array VALUE  Like -a, but a :WEIGHT suffix weighs random picks
+                                (paid:80,pending:15,refunded:5); quote or escape a
+                                colon that belongs to the value
+        -A, --pattern-array VALUE
+                                Like -a, but each value is a pattern generated when
+                                it is picked (ORD-\\d｛6｝,INV-[A-Z]｛2｝\\d｛4｝). Commas
+                                inside (), [] and ｛｝ stay in the pattern
+        --array-file SPEC       Reads an array from a file, one value per line, skipping
+                                empty lines and lines starting with #. SPEC is
+                                [NAME[:COLUMN[:WEIGHT]]=]PATH; COLUMN reads the file as
+                                CSV and takes that column by number or header name,
+                                and WEIGHT the column that weighs each value. A PATH
+                                of - reads standard input
+        --table NAME=PATH       Reads a CSV file with a header line as a table whose
+                                columns \\a｛NAME.COLUMN｝ reads; all columns in one
+                                string come from the same row. - reads standard input
+        --library PATH          Reads macros for \\m｛NAME｝ from a file of NAME = PATTERN
+                                lines; repeat the option for several files
+        -n, --count N           Number of strings to generate (default 1)
+        --seed N                Seed for reproducible output
+        --clock TIME            Time of \\u｛uuid7｝, \\u｛ulid｝ and \\u｛snowflake｝: system
+                                (default), milliseconds since 1970 or a time such
+                                as 2024-01-01T12:00:00Z
+        --state-file PATH       Continues counters, ordered arrays and --unique from
+                                the state saved in PATH and saves it there at exit
+        --unique                Never repeats a string; fails once all are used
+        --distribution MODE     per-node (default) or uniform over all strings
+        --strategy MODE         random (default), boundary, or mixed:P to pick
+                                boundary values with probability P
+        --cover                 Prints a small set of strings that reach every branch,
+                                repeat bound and range endpoint, with what each covers
+        --pairwise              Prints strings in which every pair of alternation
+                                branches and array values appears at least once
+        --enumerate             Lists every string the pattern can produce, one per line
+        --order ORDER           Enumeration order: shortlex (default) or lex
+        --limit N               Stops enumerating after N strings
+        --max-length N          Skips enumerated strings longer than N characters
     
     PATTERN:
         The pattern to be used for generating the string.
@@ -37,12 +88,34 @@ fn print_help() {
     \\t           Tab character
     \\n           Newline character
     \\i           Incrementing value (use with optional ｛:length｝ for leading zeros)
+    \\i｛name｝     Named counter set with -i name=START (also ｛name:length｝)
+    \\I           Current value of the counter, without advancing it (also \\I｛name｝)
+    \\i｛step=N｝   Counter options in the pattern: step=N, min=N, max=N, stop,
+                  wrap, clamp, alphabet=NAME (e.g. \\i+｛order:4,step=10,max=9999,wrap｝)
+    \\r｛18..65｝   Random number in a range; decimals like ｛0.00..9.99｝ keep their
+                  precision. Add width=N, thousands[=SEP], normal, mean=X, sd=X
+    \\T｛...｝      Timestamp: ｛START,+15m,FORMAT｝ steps from START, ｛START..END,FORMAT｝
+                  is random between the bounds; add tz=+HH:MM to show another offset
+    \\u｛uuid4｝    Identifier: uuid4, uuid7, ulid, nanoid (also ｛nanoid:LENGTH｝) or
+                  snowflake (also ｛snowflake:WORKER｝); see --clock
     \\a           Random string from an array (use with optional + or - for order)
+    \\a~          Each array value once per pass, reshuffled for every pass
+    \\m｛name｝     Macro: a pattern defined in (?(DEFINE)(?<name>...)), a --library
+                  file or the standard library (email, uuid, ipv4, ipv6, mac,
+                  hex_color, semver, slug, octet, hex)
+    \\a｛t.col｝    Column of the table t set with --table; one row per string
+    \\a｛@name｝    Word from a bundled dictionary (first_name, last_name, city, country,
+                  street_suffix, word, company_suffix) with the dictionaries feature
+    \\a｛name｝     String from the named array set with -a name=V1,V2 (also \\a｛name｝+)
+    \\a+｛cursor=c｝ Ordered occurrences keep their own position unless they name
+                  the same cursor (also \\a+｛name,cursor=c｝)
     [abc]         Any one of the characters a, b, or c
     [a-z]         Any character in the range a to z
     [^a-z]        Any character not in the range a to z
     ｛n｝           Exactly n repetitions of the previous element
     ｛n,m｝         Between n and m repetitions of the previous element
+    ｛n,｝          At least n repetitions of the previous element
+    *, +, ?       Zero or more, one or more, zero or one repetitions
     ｛n:m｝         Between n and m repetitions with leading zeros
     (abc)         Capture group for abc
     a|b           Alternation (matches either a or b)
@@ -51,6 +124,8 @@ fn print_help() {
     Example:
         regex_generator -p '\\i｛:10｝' -i 43
         regex_generator -p '[A-Za-z]｛5｝' -a 'apple,banana,grape'
+        regex_generator -p '[A-C]\\d｛2｝' --enumerate
+        regex_generator -p 'ORD\\i｛order｝-\\i｛line:3｝' -i order=1000 -i line=0
 "
     );
 }
@@ -73,8 +148,25 @@ fn main() {
     }
 
     let mut pattern = String::new();
-    let mut increment_value: Option<String> = None;
-    let mut array_values: Option<Vec<String>> = None;
+    let mut counters: Vec<(String, Counter)> = Vec::new();
+    let mut arrays: Vec<Array> = Vec::new();
+    let mut pattern_arrays: Vec<(String, Vec<String>)> = Vec::new();
+    let mut tables: Vec<Table> = Vec::new();
+    let mut libraries: Vec<String> = Vec::new();
+    let mut enumerate = false;
+    let mut cover = false;
+    let mut pairwise = false;
+    let mut order = Order::Shortlex;
+    let mut limit: Option<usize> = None;
+    let mut max_len: Option<usize> = None;
+    let mut count: usize = 1;
+    let mut seed: Option<u64> = None;
+    let mut unique = false;
+    let mut distribution = Distribution::PerNode;
+    let mut strategy = Strategy::Random;
+    let mut clock = Clock::System;
+    let mut counter_mode = CounterMode::PerOccurrence;
+    let mut state_file: Option<String> = None;
 
     let mut i = 1;
     while i < args.len() {
@@ -90,22 +182,175 @@ fn main() {
             }
             "-i" | "--increment" => {
                 if i + 1 < args.len() {
-                    increment_value = Some(args[i + 1].clone());
+                    match Counter::parse(&args[i + 1]) {
+                        Ok(counter) => counters.push(counter),
+                        Err(err) => {
+                            eprintln!("Error: {}", err);
+                            process::exit(1);
+                        }
+                    }
                     i += 1;
                 } else {
                     eprintln!("Error: No increment value provided.");
                     process::exit(1);
                 }
             }
-            "-a" | "--array" => {
+            "--counter-mode" => {
+                counter_mode = match args.get(i + 1).map(String::as_str) {
+                    Some("per-occurrence") => CounterMode::PerOccurrence,
+                    Some("per-call") => CounterMode::PerCall,
+                    _ => {
+                        eprintln!("Error: --counter-mode expects 'per-occurrence' or 'per-call'.");
+                        process::exit(1);
+                    }
+                };
+                i += 1;
+            }
+            "-a" | "--array" | "--weighted-array" => {
                 if i + 1 < args.len() {
-                    array_values = Some(args[i + 1].split(',').map(|s| s.to_string()).collect());
+                    match parse_array(&args[i + 1], args[i] == "--weighted-array") {
+                        Ok(array) => arrays.push(array),
+                        Err(err) => {
+                            eprintln!("Error: {}", err);
+                            process::exit(1);
+                        }
+                    }
                     i += 1;
                 } else {
                     eprintln!("Error: No array provided.");
                     process::exit(1);
                 }
             }
+            "-A" | "--pattern-array" => {
+                if i + 1 < args.len() {
+                    pattern_arrays.push(parse_pattern_array(&args[i + 1]));
+                    i += 1;
+                } else {
+                    eprintln!("Error: No pattern array provided.");
+                    process::exit(1);
+                }
+            }
+            "--array-file" => {
+                if i + 1 < args.len() {
+                    match read_array_file(&args[i + 1]) {
+                        Ok(array) => arrays.push(array),
+                        Err(err) => {
+                            eprintln!("Error: {}", err);
+                            process::exit(1);
+                        }
+                    }
+                    i += 1;
+                } else {
+                    eprintln!("Error: No array file provided.");
+                    process::exit(1);
+                }
+            }
+            "--table" => {
+                if i + 1 < args.len() {
+                    match read_table(&args[i + 1]) {
+                        Ok(table) => tables.push(table),
+                        Err(err) => {
+                            eprintln!("Error: {}", err);
+                            process::exit(1);
+                        }
+                    }
+                    i += 1;
+                } else {
+                    eprintln!("Error: No table provided.");
+                    process::exit(1);
+                }
+            }
+            "--library" => {
+                if i + 1 < args.len() {
+                    match read_input(&args[i + 1]) {
+                        Ok(text) => libraries.push(text),
+                        Err(err) => {
+                            eprintln!("Error: cannot read library {}: {}", args[i + 1], err);
+                            process::exit(1);
+                        }
+                    }
+                    i += 1;
+                } else {
+                    eprintln!("Error: No library provided.");
+                    process::exit(1);
+                }
+            }
+            "-n" | "--count" => {
+                count = parse_number(&args, i, "--count");
+                i += 1;
+            }
+            "--seed" => {
+                seed = Some(parse_number(&args, i, "--seed"));
+                i += 1;
+            }
+            "--state-file" => {
+                if i + 1 < args.len() {
+                    state_file = Some(args[i + 1].clone());
+                    i += 1;
+                } else {
+                    eprintln!("Error: No state file provided.");
+                    process::exit(1);
+                }
+            }
+            "--unique" => unique = true,
+            "--clock" => {
+                clock = match args.get(i + 1).map(String::as_str).and_then(Clock::parse) {
+                    Some(clock) => clock,
+                    None => {
+                        eprintln!("Error: --clock expects 'system', milliseconds since 1970 or a time such as 2024-01-01T12:00:00Z.");
+                        process::exit(1);
+                    }
+                };
+                i += 1;
+            }
+            "--distribution" => {
+                distribution = match args.get(i + 1).map(String::as_str) {
+                    Some("per-node") => Distribution::PerNode,
+                    Some("uniform") => Distribution::UniformLanguage,
+                    _ => {
+                        eprintln!("Error: --distribution expects 'per-node' or 'uniform'.");
+                        process::exit(1);
+                    }
+                };
+                i += 1;
+            }
+            "--strategy" => {
+                let value = args.get(i + 1).map(String::as_str).unwrap_or("");
+                strategy = match value {
+                    "random" => Strategy::Random,
+                    "boundary" => Strategy::Boundary,
+                    _ => match value.strip_prefix("mixed:").and_then(|p| p.parse::<f64>().ok()) {
+                        Some(probability) if (0.0..=1.0).contains(&probability) => Strategy::Mixed(probability),
+                        _ => {
+                            eprintln!("Error: --strategy expects 'random', 'boundary' or 'mixed:P' with P between 0 and 1.");
+                            process::exit(1);
+                        }
+                    },
+                };
+                i += 1;
+            }
+            "--cover" => cover = true,
+            "--pairwise" => pairwise = true,
+            "--enumerate" => enumerate = true,
+            "--order" => {
+                order = match args.get(i + 1).map(String::as_str) {
+                    Some("shortlex") => Order::Shortlex,
+                    Some("lex") => Order::Lexicographic,
+                    _ => {
+                        eprintln!("Error: --order expects 'shortlex' or 'lex'.");
+                        process::exit(1);
+                    }
+                };
+                i += 1;
+            }
+            "--limit" => {
+                limit = Some(parse_number(&args, i, "--limit"));
+                i += 1;
+            }
+            "--max-length" => {
+                max_len = Some(parse_number(&args, i, "--max-length"));
+                i += 1;
+            }
             _ => {
                 eprintln!("Error: Unknown option or missing value for {}", args[i]);
                 process::exit(1);
@@ -119,16 +364,256 @@ fn main() {
         process::exit(1);
     }
 
-    let mut generator = RegexGenerator::new(&pattern, increment_value, array_values);
-    let result = generator.generate();
-    println!("{}", result);
+    let mut generator = RegexGenerator::new(&pattern, None, None);
+    for library in libraries {
+        if let Err(err) = generator.load_library(&library) {
+            eprintln!("Error: {}.", err);
+            process::exit(1);
+        }
+    }
+    for (name, values, weights) in arrays {
+        generator.set_array(&name, values);
+        if let Some(weights) = weights {
+            if let Err(err) = generator.set_array_weights(&name, weights) {
+                eprintln!("Error: {}.", err);
+                process::exit(1);
+            }
+        }
+    }
+    for (name, patterns) in pattern_arrays {
+        generator.set_pattern_array(&name, patterns);
+    }
+    for (name, columns, rows) in tables {
+        if let Err(err) = generator.set_table(&name, columns, rows) {
+            eprintln!("Error: {}.", err);
+            process::exit(1);
+        }
+    }
+    for (name, counter) in counters {
+        generator.set_counter(&name, counter);
+    }
+    generator.set_counter_mode(counter_mode);
+    if let Some(seed) = seed {
+        generator.set_seed(seed);
+    }
+    generator.set_distribution(distribution);
+    generator.set_strategy(strategy);
+    generator.set_clock(clock);
+    if let Some(path) = &state_file {
+        match load_state(path) {
+            Ok(Some(state)) => generator.restore_state(state),
+            Ok(None) => {}
+            Err(err) => {
+                eprintln!("Error: cannot read state file {}: {}", path, err);
+                process::exit(1);
+            }
+        }
+    }
+
+    if cover {
+        let failure = match generator.coverage_set() {
+            Ok(set) => {
+                for coverage in set {
+                    println!("{}\t{}", coverage.value, coverage.covers.join("; "));
+                }
+                None
+            }
+            Err(err) => Some(err.to_string()),
+        };
+        finish(&generator, state_file.as_deref(), failure);
+        return;
+    }
+
+    if pairwise {
+        let failure = match generator.pairwise_set() {
+            Ok(set) => {
+                for value in set {
+                    println!("{}", value);
+                }
+                None
+            }
+            Err(err) => Some(err.to_string()),
+        };
+        finish(&generator, state_file.as_deref(), failure);
+        return;
+    }
+
+    if enumerate {
+        let strings = match generator.enumerate(order, max_len) {
+            Ok(strings) => strings,
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                process::exit(1);
+            }
+        };
+        let mut out = io::BufWriter::new(io::stdout().lock());
+        for string in strings.take(limit.unwrap_or(usize::MAX)) {
+            // Stop quietly when the reader goes away (e.g. piped into `head`).
+            if writeln!(out, "{}", string).is_err() {
+                return;
+            }
+        }
+        let _ = out.flush();
+        return;
+    }
+
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    let mut failure = None;
+    for _ in 0..count {
+        let result = if unique {
+            generator.generate_unique()
+        } else {
+            generator.try_generate()
+        };
+        match result {
+            Ok(result) => {
+                if writeln!(out, "{}", result).is_err() {
+                    break;
+                }
+            }
+            Err(err) => {
+                failure = Some(err.to_string());
+                break;
+            }
+        }
+    }
+    let _ = out.flush();
+    finish(&generator, state_file.as_deref(), failure);
+}
+
+/// Saves the state if a state file was given, then reports `failure` and
+/// exits with an error status if there was one.
+fn finish(generator: &RegexGenerator, state_file: Option<&str>, failure: Option<String>) {
+    if let Some(path) = state_file {
+        if let Err(err) = save_state(&generator.state(), path) {
+            eprintln!("Error: cannot write state file {}: {}", path, err);
+            process::exit(1);
+        }
+    }
+    if let Some(err) = failure {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    }
+}
+
+/// Reads a saved state; a missing file means there is nothing to continue.
+fn load_state(path: &str) -> io::Result<Option<GeneratorState>> {
+    match fs::read_to_string(path) {
+        Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Writes the state to a temporary file next to `path` and renames it over
+/// `path`, so an interrupted run never leaves a half-written state. The
+/// temporary name carries the process id, so concurrent runs sharing a state
+/// file never write into each other's temporary file.
+fn save_state(state: &GeneratorState, path: &str) -> io::Result<()> {
+    let temporary = format!("{}.{}.tmp", path, process::id());
+    let written = write_state(state, &temporary).and_then(|()| fs::rename(&temporary, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    written
+}
+
+fn write_state(state: &GeneratorState, path: &str) -> io::Result<()> {
+    let mut file = fs::File::create(path)?;
+    serde_json::to_writer_pretty(&mut file, state)?;
+    file.write_all(b"\n")?;
+    file.sync_all()
+}
+
+/// An array name (empty for a plain `\a`), its values and their weights.
+type Array = (String, Vec<String>, Option<Vec<f64>>);
+
+/// A table name, its columns and its rows.
+type Table = (String, Vec<String>, Vec<Vec<String>>);
+
+/// Splits an `-a` value into the array name and its values, or a
+/// `--weighted-array` value into the array name, its values and their weights.
+fn parse_array(spec: &str, weighted: bool) -> Result<Array, advanced_string_generator::Error> {
+    let (name, values) = match spec.split_once('=') {
+        Some((name, values)) if is_valid_name(name) => (name, values),
+        _ => ("", spec),
+    };
+    let (values, weights) = if weighted { parse_weighted_array_list(values)? } else { (parse_array_list(values)?, None) };
+    Ok((name.to_string(), values, weights))
+}
+
+/// Splits a `-A` value into the array name and its patterns.
+fn parse_pattern_array(spec: &str) -> (String, Vec<String>) {
+    match spec.split_once('=') {
+        Some((name, patterns)) if is_valid_name(name) => (name.to_string(), parse_pattern_list(patterns)),
+        _ => (String::new(), parse_pattern_list(spec)),
+    }
+}
+
+/// Reads the array an `--array-file [NAME[:COLUMN[:WEIGHT]]=]PATH` value
+/// points to.
+fn read_array_file(spec: &str) -> Result<Array, String> {
+    let (name, column, path) = match spec.split_once('=') {
+        Some((target, path)) => match target.split_once(':') {
+            Some((name, column)) if is_valid_name(name) && !column.is_empty() => (name, Some(column), path),
+            None if is_valid_name(target) => (target, None, path),
+            _ => ("", None, spec),
+        },
+        None => ("", None, spec),
+    };
+    let (column, weight_column) = match column.and_then(|column| column.split_once(':')) {
+        Some((column, weight_column)) => (Some(column), Some(weight_column)),
+        None => (column, None),
+    };
+    let text = read_input(path).map_err(|err| format!("cannot read array file {}: {}", path, err))?;
+    let (values, weights) = match (column, weight_column) {
+        (Some(column), Some(weight_column)) => {
+            let (values, weights) = parse_weighted_array_file(&text, column, weight_column).map_err(|err| format!("{} ({})", err, path))?;
+            (values, Some(weights))
+        }
+        _ => (parse_array_file(&text, column).map_err(|err| format!("{} ({})", err, path))?, None),
+    };
+    Ok((name.to_string(), values, weights))
+}
+
+/// Reads the table a `--table NAME=PATH` value points to.
+fn read_table(spec: &str) -> Result<Table, String> {
+    let Some((name, path)) = spec.split_once('=').filter(|(name, _)| is_valid_name(name)) else {
+        return Err(format!("--table expects NAME=PATH, not '{}'", spec));
+    };
+    let text = read_input(path).map_err(|err| format!("cannot read table {}: {}", path, err))?;
+    let (columns, rows) = parse_table(&text).map_err(|err| format!("{} ({})", err, path))?;
+    Ok((name.to_string(), columns, rows))
+}
+
+/// Reads a file, or standard input for `-`.
+fn read_input(path: &str) -> io::Result<String> {
+    if path == "-" {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)?;
+        Ok(text)
+    } else {
+        fs::read_to_string(path)
+    }
+}
+
+fn parse_number<T: FromStr>(args: &[String], i: usize, option: &str) -> T {
+    match args.get(i + 1).and_then(|value| value.parse().ok()) {
+        Some(value) => value,
+        None => {
+            eprintln!("Error: {} expects a non-negative number.", option);
+            process::exit(1);
+        }
+    }
 }
 
 
 
 #[cfg(test)]
+#[allow(clippy::is_digit_ascii_radix, clippy::iter_nth_zero, clippy::manual_range_contains)]
 mod tests {
     use super::*;
+    use advanced_string_generator::{parse_array_list, parse_library, standard_library, Alphabet, Error};
 
     #[test]
     fn test_increment_ascending() {
@@ -236,7 +721,8 @@ mod tests {
             print!("{}", generated);
             assert!(generated.len() == 3);
             assert!(generated.chars().nth(0).unwrap().is_digit(10));
-            assert!(generated.chars().nth(1).unwrap().is_alphanumeric());
+            let word_char = generated.chars().nth(1).unwrap();
+            assert!(word_char.is_alphanumeric() || word_char == '_');
             assert!(generated.chars().nth(2).unwrap().is_whitespace());
         }
     }
@@ -325,7 +811,840 @@ mod tests {
 
     }
 
+    #[test]
+    fn test_enumerate_shortlex() {
+        let generator = RegexGenerator::new(r"[A-C]\d{2}", None, None);
+        let all: Vec<String> = generator.enumerate(Order::Shortlex, None).unwrap().collect();
+        assert_eq!(all.len(), 300);
+        assert_eq!(all[0], "A00");
+        assert_eq!(all[1], "A01");
+        assert_eq!(all[299], "C99");
 
+        let generator = RegexGenerator::new(r"(ab|a)(c|bc)", None, None);
+        let all: Vec<String> = generator.enumerate(Order::Shortlex, None).unwrap().collect();
+        assert_eq!(all, vec!["ac", "abc", "abbc"]);
+    }
 
-}
+    #[test]
+    fn test_enumerate_lexicographic() {
+        let generator = RegexGenerator::new(r"x\d{0,1}|\a", None, Some(vec!["w".to_string(), "x".to_string()]));
+        let all: Vec<String> = generator.enumerate(Order::Lexicographic, None).unwrap().collect();
+        let expected: Vec<String> = ["w", "x", "x0", "x1", "x2", "x3", "x4", "x5", "x6", "x7", "x8", "x9"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(all, expected);
+    }
+
+    #[test]
+    fn test_enumerate_infinite() {
+        let generator = RegexGenerator::new(r"a*b", None, None);
+        let first: Vec<String> = generator.enumerate(Order::Shortlex, None).unwrap().take(3).collect();
+        assert_eq!(first, vec!["b", "ab", "aab"]);
+        assert!(generator.enumerate(Order::Lexicographic, None).is_err());
+
+        let capped: Vec<String> = generator.enumerate(Order::Lexicographic, Some(3)).unwrap().collect();
+        assert_eq!(capped, vec!["aab", "ab", "b"]);
+    }
+
+    #[test]
+    fn test_enumerate_unsupported() {
+        let generator = RegexGenerator::new(r"(a)\1", None, None);
+        assert!(generator.enumerate(Order::Shortlex, None).is_err());
+    }
+
+    #[test]
+    fn test_nth_and_index_of() {
+        let generator = RegexGenerator::new(r"[A-C]\d{2}", None, None);
+        assert_eq!(generator.cardinality(), Ok(Some(300)));
+        assert_eq!(generator.nth(0).as_deref(), Some("A00"));
+        assert_eq!(generator.nth(123).as_deref(), Some("B23"));
+        assert_eq!(generator.nth(299).as_deref(), Some("C99"));
+        assert_eq!(generator.nth(300), None);
+        assert_eq!(generator.index_of("B23"), Some(123));
+        assert_eq!(generator.index_of("D00"), None);
+
+        let all: Vec<String> = generator.enumerate(Order::Shortlex, None).unwrap().collect();
+        for (index, value) in all.iter().enumerate() {
+            assert_eq!(generator.nth(index as u128).as_ref(), Some(value));
+            assert_eq!(generator.index_of(value), Some(index as u128));
+        }
+
+        // Counts for lengths far apart are cached in blocks.
+        let generator = RegexGenerator::new(r"x{100,140}[ab]", None, None);
+        let all: Vec<String> = generator.enumerate(Order::Shortlex, None).unwrap().collect();
+        assert_eq!(generator.cardinality(), Ok(Some(82)));
+        for (index, value) in all.iter().enumerate().rev() {
+            assert_eq!(generator.nth(index as u128).as_ref(), Some(value));
+            assert_eq!(generator.index_of(value), Some(index as u128));
+        }
+
+        let generator = RegexGenerator::new(r"x{20000}", None, None);
+        assert_eq!(generator.cardinality(), Ok(Some(1)));
+        assert_eq!(generator.nth(0).map(|value| value.len()), Some(20000));
+        assert_eq!(generator.index_of(&"x".repeat(20000)), Some(0));
+
+        let generator = RegexGenerator::new(r"x|[a-z]{28}", None, None);
+        assert_eq!(generator.cardinality(), Ok(None));
+        for index in [0, 1, 2, u128::MAX / 2, u128::MAX] {
+            let value = generator.nth(index).unwrap();
+            assert_eq!(generator.index_of(&value), Some(index), "{}", value);
+        }
+        assert_eq!(generator.index_of(&"z".repeat(28)), None);
+        let generator = RegexGenerator::new(r"[ab][a-z]{28}", None, None);
+        assert_eq!(generator.nth(u128::MAX), None);
+    }
+
+    #[test]
+    fn test_nth_infinite() {
+        let generator = RegexGenerator::new(r"(ab|a)*", None, None);
+        assert_eq!(generator.cardinality(), Ok(None));
+        let first: Vec<String> = generator.enumerate(Order::Shortlex, None).unwrap().take(20).collect();
+        for (index, value) in first.iter().enumerate() {
+            assert_eq!(generator.nth(index as u128).as_ref(), Some(value));
+            assert_eq!(generator.index_of(value), Some(index as u128));
+        }
+    }
+
+    #[test]
+    fn test_unique() {
+        let mut generator = RegexGenerator::new(r"[A-C]\d", None, None);
+        generator.set_seed(7);
+        let mut seen: Vec<String> = (0..30).map(|_| generator.generate_unique().unwrap()).collect();
+        assert_eq!(generator.generate_unique(), Err(Error::Exhausted));
+
+        seen.sort();
+        let all: Vec<String> = generator.enumerate(Order::Lexicographic, None).unwrap().collect();
+        assert_eq!(seen, all);
+    }
+
+    #[test]
+    fn test_unique_seeded_order() {
+        let mut first = RegexGenerator::new(r"\d{3}", None, None);
+        let mut second = RegexGenerator::new(r"\d{3}", None, None);
+        first.set_seed(42);
+        second.set_seed(42);
+        for _ in 0..20 {
+            assert_eq!(first.generate_unique(), second.generate_unique());
+        }
+
+        let mut infinite = RegexGenerator::new(r"a+", None, None);
+        assert_eq!(infinite.generate_unique(), Err(Error::InfiniteLanguage));
+    }
+
+    #[test]
+    fn test_uniform_language_distribution() {
+        // One string for the first branch, five for the second.
+        let mut generator = RegexGenerator::new(r"(a|bcdef{1,5})", None, None);
+        generator.set_seed(1);
+        generator.set_distribution(Distribution::UniformLanguage);
+        let short = (0..6000).filter(|_| generator.generate() == "a").count();
+        assert!((800..1200).contains(&short), "got {} short strings", short);
+
+        // 10 one-digit strings against 100 two-digit strings.
+        let mut generator = RegexGenerator::new(r"\d{1,2}", None, None);
+        generator.set_seed(1);
+        generator.set_distribution(Distribution::UniformLanguage);
+        let short = (0..11000).filter(|_| generator.generate().len() == 1).count();
+        assert!((800..1200).contains(&short), "got {} short strings", short);
+    }
+
+    #[test]
+    fn test_uniform_language_ambiguous() {
+        // "a" can be built from either a?, but is only one of three strings.
+        let mut generator = RegexGenerator::new(r"a?a?", None, None);
+        generator.set_seed(1);
+        generator.set_distribution(Distribution::UniformLanguage);
+        let mut counts = std::collections::HashMap::new();
+        for _ in 0..9000 {
+            *counts.entry(generator.generate()).or_insert(0) += 1;
+        }
+        for value in ["", "a", "aa"] {
+            assert!((2700..3300).contains(&counts[value]), "{:?}", counts);
+        }
+
+        let mut generator = RegexGenerator::new(r"[ab]|a", None, None);
+        generator.set_seed(1);
+        generator.set_distribution(Distribution::UniformLanguage);
+        let a = (0..6000).filter(|_| generator.generate() == "a").count();
+        assert!((2700..3300).contains(&a), "got {} a", a);
+    }
+
+    #[test]
+    fn test_boundary_strategy() {
+        let array_values = Some(vec!["kiwi".to_string(), "fig".to_string(), "banana".to_string()]);
+        let mut generator = RegexGenerator::new(r"[a-z]{2,5}-(|x)-\d{3:5}-\a", None, array_values);
+        generator.set_strategy(Strategy::Boundary);
+
+        for _ in 0..20 {
+            let generated = generator.generate();
+            let parts: Vec<&str> = generated.split('-').collect();
+            assert!(parts[0].len() == 2 || parts[0].len() == 5);
+            assert!(parts[0].chars().all(|c| c == 'a' || c == 'z'));
+            assert_eq!(parts[1], "");
+            assert!(parts[2] == "00100" || parts[2] == "00999");
+            assert!(parts[3] == "fig" || parts[3] == "banana");
+        }
+    }
+
+    #[test]
+    fn test_mixed_strategy() {
+        let mut generator = RegexGenerator::new(r"[a-y]{1,9}", None, None);
+        generator.set_strategy(Strategy::Mixed(0.0));
+        assert!((0..50).any(|_| {
+            let generated = generator.generate();
+            generated.len() != 1 && generated.len() != 9
+        }));
 
+        generator.set_strategy(Strategy::Mixed(1.0));
+        for _ in 0..20 {
+            let generated = generator.generate();
+            assert!(generated.chars().all(|c| c == 'a' || c == 'y'));
+        }
+
+        for probability in [f64::NAN, f64::NEG_INFINITY, f64::INFINITY] {
+            generator.set_strategy(Strategy::Mixed(probability));
+            let generated = generator.generate();
+            assert!((1..=9).contains(&generated.len()), "{}", generated);
+        }
+    }
+
+    #[test]
+    fn test_coverage_set() {
+        let mut generator = RegexGenerator::new(r"(GET|POST) [a-c]{1,3}x?", None, None);
+        let set = generator.coverage_set().unwrap();
+        let values: Vec<&str> = set.iter().map(|coverage| coverage.value.as_str()).collect();
+        let covers: Vec<&String> = set.iter().flat_map(|coverage| &coverage.covers).collect();
+
+        assert!(values.iter().any(|value| value.starts_with("GET ")));
+        assert!(values.iter().any(|value| value.starts_with("POST ")));
+        for length in 1..=3 {
+            assert!(values.iter().any(|value| value.split(' ').nth(1).unwrap().trim_end_matches('x').len() == length));
+        }
+        assert!(values.iter().any(|value| value.ends_with('x')));
+        assert!(values.iter().any(|value| !value.ends_with('x')));
+        assert!(values.iter().any(|value| value.contains('a')));
+        assert!(values.iter().any(|value| value.contains('c')));
+        assert!(set.len() <= 4);
+
+        assert!(covers.contains(&&"(GET|POST) -> POST".to_string()));
+        assert!(covers.contains(&&"[a-c]{1,3} -> count 2".to_string()));
+        assert!(covers.contains(&&"[a-c] -> 'c'".to_string()));
+        assert!(covers.contains(&&"x? -> count 0".to_string()));
+    }
+
+    #[test]
+    fn test_pairwise_set() {
+        let array_values = Some(vec!["dev".to_string(), "staging".to_string(), "prod".to_string()]);
+        let mut generator = RegexGenerator::new(r"(GET|POST) \a (v1|v2|v3)", None, array_values);
+        let set = generator.pairwise_set().unwrap();
+        assert!(set.len() < 18, "{} cases is no better than all combinations", set.len());
+
+        let cases: Vec<Vec<&str>> = set.iter().map(|value| value.split(' ').collect()).collect();
+        for method in ["GET", "POST"] {
+            for env in ["dev", "staging", "prod"] {
+                assert!(cases.iter().any(|case| case[0] == method && case[1] == env));
+            }
+            for version in ["v1", "v2", "v3"] {
+                assert!(cases.iter().any(|case| case[0] == method && case[2] == version));
+            }
+        }
+        for env in ["dev", "staging", "prod"] {
+            for version in ["v1", "v2", "v3"] {
+                assert!(cases.iter().any(|case| case[1] == env && case[2] == version));
+            }
+        }
+    }
+
+    #[test]
+    fn test_pairwise_nested_choices() {
+        // (x|y) only exists inside the second branch, so it never pairs with "a".
+        let mut generator = RegexGenerator::new(r"(a|b(x|y))(1|2)", None, None);
+        let set = generator.pairwise_set().unwrap();
+        for expected in ["a1", "a2", "bx1", "bx2", "by1", "by2"] {
+            assert!(set.iter().any(|value| value == expected), "missing {} in {:?}", expected, set);
+        }
+    }
+
+    #[test]
+    fn test_named_counters() {
+        let mut generator = RegexGenerator::new(r"\i{order}-\i{line:3}-\i-{order}", None, None);
+        generator.set_counter("order", Counter::new("1000"));
+        generator.set_counter("line", Counter::new("0"));
+
+        let expected_results = vec!["1001-001-1000", "1001-002-1000"];
+        for expected in expected_results {
+            assert_eq!(generator.generate(), expected);
+        }
+        assert_eq!(generator.counter("line").unwrap().value, "002");
+    }
+
+    #[test]
+    fn test_counter_spec() {
+        let (name, counter) = Counter::parse("order=1000,desc,width=6").unwrap();
+        assert_eq!(name, "order");
+        assert_eq!(counter, Counter { direction: -1, width: Some(6), ..Counter::new("1000") });
+
+        let (name, counter) = Counter::parse("INV0099").unwrap();
+        assert_eq!(name, "");
+        assert_eq!(counter, Counter::new("INV0099"));
+
+        assert!(Counter::parse("order=1,sideways").is_err());
+
+        let mut generator = RegexGenerator::new(r"\i{order}", None, None);
+        let (name, counter) = Counter::parse("order=10,desc,width=4").unwrap();
+        generator.set_counter(&name, counter);
+        assert_eq!(generator.generate(), "0009");
+        assert_eq!(generator.generate(), "0008");
+    }
+
+    #[test]
+    fn test_counter_step_and_bounds() {
+        let mut generator = RegexGenerator::new(r"\i+{step=10}", Some("0".to_string()), None);
+        assert_eq!(generator.generate(), "10");
+        assert_eq!(generator.generate(), "20");
+
+        let mut generator = RegexGenerator::new(r"\i{:4,max=9999,wrap}", Some("9998".to_string()), None);
+        let values: Vec<String> = (0..3).map(|_| generator.generate()).collect();
+        assert_eq!(values, ["9999", "0000", "0001"]);
+
+        let mut generator = RegexGenerator::new(r"\i-{min=1,wrap,max=3}", Some("2".to_string()), None);
+        let values: Vec<String> = (0..3).map(|_| generator.generate()).collect();
+        assert_eq!(values, ["1", "3", "2"]);
+
+        let mut generator = RegexGenerator::new(r"\i{order}", None, None);
+        let (name, counter) = Counter::parse("order=5,step=5,max=12,clamp").unwrap();
+        generator.set_counter(&name, counter);
+        let values: Vec<String> = (0..3).map(|_| generator.generate()).collect();
+        assert_eq!(values, ["10", "12", "12"]);
+
+        let mut generator = RegexGenerator::new(r"\i{order}", None, None);
+        generator.set_counter("order", Counter::parse("8,step=2,max=10").unwrap().1);
+        assert_eq!(generator.try_generate(), Ok("10".to_string()));
+        assert_eq!(generator.try_generate(), Err(Error::CounterOutOfRange("order".to_string())));
+        assert_eq!(generator.counter("order").unwrap().value, "10");
+        assert!(Counter::parse("n=5,min=3,desc,wrap").is_err());
+        assert!(Counter::parse("n=5,max=9,desc,wrap").is_ok());
+        let mut generator = RegexGenerator::new(r"\i{stpe=10} \i+{min=1,wrap} \i{:3,step=2}", Some("1".to_string()), None);
+        assert_eq!(generator.generate(), "i{stpe=10} i+{min=1,wrap} 003");
+
+        let mut generator = RegexGenerator::new(r"(a|b)(c|d)\i{order}", None, None);
+        generator.set_counter("order", Counter::parse("10,max=10").unwrap().1);
+        assert_eq!(generator.coverage_set().map(|set| set.len()), Err(Error::CounterOutOfRange("order".to_string())));
+        assert_eq!(generator.pairwise_set(), Err(Error::CounterOutOfRange("order".to_string())));
+    }
+
+    #[test]
+    fn test_counter_precision_and_suffix() {
+        let mut generator = RegexGenerator::new(r"\i", Some("INV0099-EU".to_string()), None);
+        assert_eq!(generator.generate(), "INV0100-EU");
+
+        let mut generator = RegexGenerator::new(r"\i", Some("99999999999999999999999999999999999999999999".to_string()), None);
+        assert_eq!(generator.generate(), "100000000000000000000000000000000000000000000");
+
+        let mut generator = RegexGenerator::new(r"\i-", Some("100000000000000000000000000000000000000000000".to_string()), None);
+        assert_eq!(generator.generate(), "99999999999999999999999999999999999999999999");
+
+        let mut generator = RegexGenerator::new(r"\i-", Some("1".to_string()), None);
+        let values: Vec<String> = (0..3).map(|_| generator.generate()).collect();
+        assert_eq!(values, ["0", "-1", "-2"]);
+
+        let mut generator = RegexGenerator::new(r"\i", Some("-002".to_string()), None);
+        let values: Vec<String> = (0..3).map(|_| generator.generate()).collect();
+        assert_eq!(values, ["-001", "000", "001"]);
+
+        let mut generator = RegexGenerator::new(r"\i-", Some("x10y".to_string()), None);
+        assert_eq!(generator.generate(), "x9y");
+    }
+
+    #[test]
+    fn test_counter_alphabets() {
+        let mut generator = RegexGenerator::new(r"TICKET-\i{alphabet=LETTERS}", Some("AY".to_string()), None);
+        let values: Vec<String> = (0..3).map(|_| generator.generate()).collect();
+        assert_eq!(values, ["TICKET-AZ", "TICKET-BA", "TICKET-BB"]);
+
+        let mut generator = RegexGenerator::new(r"\i{alphabet=LETTERS}", Some("ZZ".to_string()), None);
+        assert_eq!(generator.generate(), "BAA");
+
+        let mut generator = RegexGenerator::new(r"0x\i{:4,alphabet=hex}", Some("00ff".to_string()), None);
+        assert_eq!(generator.generate(), "0x0100");
+
+        let mut generator = RegexGenerator::new(r"\i-{alphabet=BASE36}", Some("K-100".to_string()), None);
+        assert_eq!(generator.generate(), "K-ZZ");
+
+        let mut generator = RegexGenerator::new(r"\i{key}", None, None);
+        let (name, counter) = Counter::parse("key=zz,alphabet=base62").unwrap();
+        assert_eq!(counter.alphabet, Alphabet::Base62);
+        generator.set_counter(&name, counter);
+        assert_eq!(generator.generate(), "100");
+
+        let mut generator = RegexGenerator::new(r"\i{dna}", None, None);
+        generator.set_counter("dna", Counter::parse("ACGT,alphabet=ACGT,max=255,wrap").unwrap().1);
+        let values: Vec<String> = (0..2).map(|_| generator.generate()).collect();
+        assert_eq!(values, ["ACTA", "ACTC"]);
+
+        assert_eq!(Alphabet::parse("aa"), None);
+        assert!(Counter::parse("1,alphabet=x").is_err());
+    }
+
+    #[test]
+    fn test_counter_mode_and_current_value() {
+        let mut generator = RegexGenerator::new(r"user\I@example.com;id=\I", Some("42".to_string()), None);
+        generator.set_counter_mode(CounterMode::PerCall);
+        assert_eq!(generator.generate(), "user43@example.com;id=43");
+        assert_eq!(generator.generate(), "user44@example.com;id=44");
+
+        let mut generator = RegexGenerator::new(r"\i,\i,\I", Some("0".to_string()), None);
+        assert_eq!(generator.generate(), "1,2,2");
+        generator.set_counter_mode(CounterMode::PerCall);
+        assert_eq!(generator.generate(), "3,3,3");
+
+        let mut generator = RegexGenerator::new(r"\I{order}/\i{order}/\I{order}", None, None);
+        generator.set_counter("order", Counter::new("7"));
+        assert_eq!(generator.generate(), "7/8/8");
+
+        // A \I ahead of the \i advances with the \i's step and bounds.
+        let mut generator = RegexGenerator::new(r"\I,\i+{step=10,max=20,clamp}", Some("0".to_string()), None);
+        generator.set_counter_mode(CounterMode::PerCall);
+        let values: Vec<String> = (0..3).map(|_| generator.generate()).collect();
+        assert_eq!(values, ["10,10", "20,20", "20,20"]);
+        assert_eq!(generator.enumerate(Order::Shortlex, None).err(), Some(Error::UnsupportedToken("\\I".to_string())));
+    }
+
+    #[test]
+    fn test_state_round_trip() {
+        let mut generator = RegexGenerator::new(r"\i{order}-\a+", None, Some(vec!["x".to_string(), "y".to_string()]));
+        generator.set_counter("order", Counter::new("INV0099"));
+        assert_eq!(generator.generate(), "INV0100-x");
+        let json = serde_json::to_string(&generator.state()).unwrap();
+
+        let mut generator = RegexGenerator::new(r"\i{order}-\a+", None, Some(vec!["x".to_string(), "y".to_string()]));
+        generator.restore_state(serde_json::from_str(&json).unwrap());
+        assert_eq!(generator.generate(), "INV0101-y");
+        assert_eq!(generator.generate(), "INV0102-x");
+
+        let mut generator = RegexGenerator::new("[ab]", None, None);
+        let first = generator.generate_unique().unwrap();
+        let path = env::temp_dir().join(format!("regex_generator_state_{}.json", process::id()));
+        let path = path.to_str().unwrap();
+        save_state(&generator.state(), path).unwrap();
+        assert!(!std::path::Path::new(&format!("{}.{}.tmp", path, process::id())).exists());
+        let mut generator = RegexGenerator::new("[ab]", None, None);
+        generator.restore_state(load_state(path).unwrap().unwrap());
+        let second = generator.generate_unique().unwrap();
+        assert_ne!(first, second);
+        assert_eq!(generator.generate_unique(), Err(Error::Exhausted));
+        fs::remove_file(path).unwrap();
+        assert_eq!(load_state(path).unwrap(), None);
+    }
+
+    #[test]
+    fn test_timestamps() {
+        let mut generator = RegexGenerator::new(r"\T{2024-01-01T23:30:00,+15m,%Y-%m-%dT%H:%M}", None, None);
+        let values: Vec<String> = (0..3).map(|_| generator.generate()).collect();
+        assert_eq!(values, ["2024-01-01T23:30", "2024-01-01T23:45", "2024-01-02T00:00"]);
+
+        let mut generator = RegexGenerator::new(r"\T{2024-03-10 12:00:00Z,-1d,tz=-05:00,%a, %d %b %H:%M %:z}", None, None);
+        assert_eq!(generator.generate(), "Sun, 10 Mar 07:00 -05:00");
+        assert_eq!(generator.generate(), "Sat, 09 Mar 07:00 -05:00");
+
+        let mut generator = RegexGenerator::new(r"\T{2024-01-01..2024-12-31,%Y-%m-%d}", None, None);
+        generator.set_seed(7);
+        for _ in 0..50 {
+            let value = generator.generate();
+            assert!(value.starts_with("2024-") && value.len() == 10, "{}", value);
+        }
+        generator.set_strategy(Strategy::Boundary);
+        for _ in 0..10 {
+            let value = generator.generate();
+            assert!(value == "2024-01-01" || value == "2024-12-31", "{}", value);
+        }
+
+        let mut generator = RegexGenerator::new(r"\T{2024-01-01,+1h,%H}/\T{2024-01-01,+1h,%H}", None, None);
+        generator.set_counter_mode(CounterMode::PerCall);
+        assert_eq!(generator.generate(), "00/00");
+        let state = generator.state();
+        let mut generator = RegexGenerator::new(r"\T{2024-01-01,+1h,%H}/\T{2024-01-01,+1h,%H}", None, None);
+        generator.restore_state(state);
+        assert_eq!(generator.generate(), "01/02");
+
+        let mut generator = RegexGenerator::new(r"\T{yesterday}", None, None);
+        assert_eq!(generator.generate(), "T{yesterday}");
+    }
+
+    #[test]
+    fn test_numeric_ranges() {
+        let mut generator = RegexGenerator::new(r"age=\r{18..65}", None, None);
+        for _ in 0..200 {
+            let age: i32 = generator.generate()[4..].parse().unwrap();
+            assert!((18..=65).contains(&age));
+        }
+
+        let mut generator = RegexGenerator::new(r"\r{-100..100}", None, None);
+        let values: Vec<i32> = (0..500).map(|_| generator.generate().parse().unwrap()).collect();
+        assert!(values.iter().all(|value| (-100..=100).contains(value)));
+        assert!(values.iter().any(|&value| value < 0));
+
+        let mut generator = RegexGenerator::new(r"\r{0.00..999.99}", None, None);
+        for _ in 0..100 {
+            let value = generator.generate();
+            let (_, fraction) = value.split_once('.').unwrap();
+            assert_eq!(fraction.len(), 2, "{}", value);
+            assert!((0.0..=999.99).contains(&value.parse::<f64>().unwrap()));
+        }
+
+        let mut generator = RegexGenerator::new(r"\r{1000000..1000000,thousands}|\r{7..7,width=3}|\r{-1234.5..-1234.5,thousands=_,width=6}", None, None);
+        generator.set_distribution(Distribution::UniformLanguage);
+        let mut seen: Vec<String> = (0..100).map(|_| generator.generate()).collect();
+        seen.sort();
+        seen.dedup();
+        assert_eq!(seen, ["-001_234.5", "007", "1,000,000"]);
+
+        let mut generator = RegexGenerator::new(r"\r{0..100,normal,sd=5}", None, None);
+        generator.set_seed(3);
+        let values: Vec<i32> = (0..1000).map(|_| generator.generate().parse().unwrap()).collect();
+        let near_mean = values.iter().filter(|value| (40..=60).contains(*value)).count();
+        assert!(near_mean > 900, "{}", near_mean);
+
+        let mut generator = RegexGenerator::new(r"\r{1..9}", None, None);
+        generator.set_strategy(Strategy::Boundary);
+        assert!((0..20).all(|_| ["1", "9"].contains(&generator.generate().as_str())));
+
+        let mut generator = RegexGenerator::new(r"\r{9..1}", None, None);
+        assert_eq!(generator.generate(), "r{9..1}");
+    }
+
+    #[test]
+    fn test_named_arrays() {
+        let mut generator = RegexGenerator::new(r"\a{first}@\a{city}+.\a{tld}-", None, None);
+        generator.set_array("first", vec!["ann".to_string()]);
+        generator.set_array("city", vec!["oslo".to_string(), "rome".to_string(), "lima".to_string()]);
+        generator.set_array("tld", vec!["com".to_string(), "org".to_string()]);
+        let values: Vec<String> = (0..3).map(|_| generator.generate()).collect();
+        assert_eq!(values, ["ann@oslo.org", "ann@rome.com", "ann@lima.org"]);
+
+        let mut generator = RegexGenerator::new(r"\a+{x}\a{3}", None, Some(vec!["z".to_string()]));
+        generator.set_array("x", vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(generator.generate(), "azzz");
+        assert_eq!(generator.generate(), "bzzz");
+        assert_eq!(generator.state().array_cursors["x"], 2);
+
+        let mut generator = RegexGenerator::new(r"(\a{m}|\a)", None, Some(vec!["p".to_string()]));
+        generator.set_array("m", vec!["q".to_string(), "r".to_string()]);
+        let strings: Vec<String> = generator.enumerate(Order::Shortlex, None).unwrap().collect();
+        assert_eq!(strings, ["p", "q", "r"]);
+
+        assert_eq!(parse_array("city=oslo,rome", false), Ok(("city".to_string(), vec!["oslo".to_string(), "rome".to_string()], None)));
+        assert_eq!(parse_array("a=b,c", false), Ok(("a".to_string(), vec!["b".to_string(), "c".to_string()], None)));
+        assert_eq!(parse_array("x+y=z,w", false), Ok((String::new(), vec!["x+y=z".to_string(), "w".to_string()], None)));
+    }
+
+    #[test]
+    fn test_array_sources() {
+        assert_eq!(parse_array_list(r#""Paris, France",Oslo\, Norway,"say ""hi""",a\\b"#).unwrap(), ["Paris, France", "Oslo, Norway", "say \"hi\"", "a\\b"]);
+        assert!(parse_array_list("\"open,close").is_err());
+
+        let text = "\u{feff}# cities\nOslo\n\n  # indented comment\nRome\r\n";
+        assert_eq!(parse_array_file(text, None).unwrap(), ["Oslo", "Rome"]);
+
+        let csv = "id,city,country\n# skipped\n1,Oslo,NO\n2,\"Washington, D.C.\",US\n";
+        assert_eq!(parse_array_file(csv, Some("city")).unwrap(), ["Oslo", "Washington, D.C."]);
+        assert_eq!(parse_array_file(csv, Some("3")).unwrap(), ["country", "NO", "US"]);
+        assert!(parse_array_file(csv, Some("4")).is_err());
+        assert!(parse_array_file(csv, Some("zip")).is_err());
+
+        let path = env::temp_dir().join(format!("regex_generator_array_{}.csv", process::id()));
+        fs::write(&path, csv).unwrap();
+        let spec = format!("where:city={}", path.display());
+        assert_eq!(read_array_file(&spec).unwrap(), ("where".to_string(), vec!["Oslo".to_string(), "Washington, D.C.".to_string()], None));
+        fs::remove_file(&path).unwrap();
+        assert!(read_array_file(&spec).is_err());
+    }
+
+    #[test]
+    fn test_weighted_choices() {
+        let count = |values: &[String], value: &str| values.iter().filter(|v| *v == value).count();
+
+        let mut generator = RegexGenerator::new(r"\a{status}", None, None);
+        generator.set_seed(5);
+        generator.set_array("status", vec!["paid".to_string(), "pending".to_string(), "refunded".to_string()]);
+        generator.set_array_weights("status", vec![80.0, 15.0, 5.0]).unwrap();
+        let values: Vec<String> = (0..10_000).map(|_| generator.generate()).collect();
+        assert!((7600..8400).contains(&count(&values, "paid")), "{}", count(&values, "paid"));
+        assert!((300..700).contains(&count(&values, "refunded")), "{}", count(&values, "refunded"));
+        assert!(generator.set_array_weights("status", vec![1.0]).is_err());
+        assert!(generator.set_array_weights("status", vec![0.0, 0.0, 0.0]).is_err());
+        assert!(generator.set_array_weights("other", vec![1.0]).is_err());
+
+        let mut generator = RegexGenerator::new(r"(<9>a|b|<0>c)", None, None);
+        generator.set_seed(5);
+        let values: Vec<String> = (0..1000).map(|_| generator.generate()).collect();
+        assert!((850..950).contains(&count(&values, "a")), "{}", count(&values, "a"));
+        assert_eq!(count(&values, "c"), 0);
+        let strings: Vec<String> = generator.enumerate(Order::Shortlex, None).unwrap().collect();
+        assert_eq!(strings, ["a", "b", "c"]);
+
+        let mut generator = RegexGenerator::new(r"(<b>|<x>)", None, None);
+        assert!(["<b>", "<x>"].contains(&generator.generate().as_str()));
+        let mut generator = RegexGenerator::new(r"<3>x|<2>y", None, None);
+        assert!(["<3>x", "<2>y"].contains(&generator.generate().as_str()));
+        let mut generator = RegexGenerator::new(r"<3>x", None, None);
+        assert_eq!(generator.generate(), "<3>x");
+
+        assert_eq!(
+            parse_array("status=paid:80,pending:15,\"12:30\",9\\:45", true),
+            Ok(("status".to_string(), vec!["paid".to_string(), "pending".to_string(), "12:30".to_string(), "9:45".to_string()], Some(vec![80.0, 15.0, 1.0, 1.0])))
+        );
+        assert_eq!(parse_array("a:b,c", true), Ok((String::new(), vec!["a:b".to_string(), "c".to_string()], None)));
+        assert_eq!(parse_array("localhost:8080,db:5432", false), Ok((String::new(), vec!["localhost:8080".to_string(), "db:5432".to_string()], None)));
+        assert_eq!(parse_array("slot=10:30,11:45", false), Ok(("slot".to_string(), vec!["10:30".to_string(), "11:45".to_string()], None)));
+
+        let csv = "status,share\npaid,80\nrefunded,5\n";
+        assert_eq!(parse_weighted_array_file(csv, "status", "share").unwrap(), (vec!["paid".to_string(), "refunded".to_string()], vec![80.0, 5.0]));
+        assert!(parse_weighted_array_file("paid,lots\n", "1", "2").is_err());
+    }
+
+    #[test]
+    fn test_array_cursor_per_occurrence() {
+        let fruits = || Some(vec!["apple".to_string(), "banana".to_string(), "cherry".to_string()]);
+        let mut generator = RegexGenerator::new(r"\a+-\a+ \a-", None, fruits());
+        let values: Vec<String> = (0..2).map(|_| generator.generate()).collect();
+        assert_eq!(values, ["apple-apple cherry", "banana-banana banana"]);
+        let state = generator.state();
+        let cursors: Vec<&str> = state.array_cursors.keys().map(String::as_str).collect();
+        assert_eq!(cursors, ["", "#2", "#3"]);
+
+        let mut generator = RegexGenerator::new(r"\a+{cursor=c}-\a+{cursor=c} \a{city,cursor=c}-", None, fruits());
+        generator.set_array("city", vec!["oslo".to_string(), "rome".to_string()]);
+        let values: Vec<String> = (0..2).map(|_| generator.generate()).collect();
+        assert_eq!(values, ["apple-banana rome", "cherry-apple oslo"]);
+        assert_eq!(generator.state().array_cursors["@c"], 4);
+
+        let mut generator = RegexGenerator::new(r"\a{3,}", None, Some(vec!["z".to_string()]));
+        assert!(generator.generate().len() >= 3);
+    }
+
+    #[test]
+    fn test_array_deck() {
+        let cards: Vec<String> = (1..=6).map(|card| card.to_string()).collect();
+        let dealt = |seed: u64, count: usize| {
+            let mut generator = RegexGenerator::new(r"\a~", None, Some(cards.clone()));
+            generator.set_seed(seed);
+            (0..count).map(|_| generator.generate()).collect::<Vec<String>>()
+        };
+        let values = dealt(7, 18);
+        for pass in values.chunks(6) {
+            let mut pass = pass.to_vec();
+            pass.sort();
+            assert_eq!(pass, cards);
+        }
+        assert_ne!(values[..6], values[6..12]);
+        assert_eq!(dealt(7, 18), values);
+        assert_ne!(dealt(8, 18), values);
+
+        let mut generator = RegexGenerator::new(r"\a~", None, Some(cards.clone()));
+        generator.set_seed(7);
+        let first: Vec<String> = (0..4).map(|_| generator.generate()).collect();
+        let mut restored = RegexGenerator::new(r"\a~", None, Some(cards.clone()));
+        restored.restore_state(generator.state());
+        let rest: Vec<String> = (0..14).map(|_| restored.generate()).collect();
+        assert_eq!([first, rest].concat(), values);
+
+        let mut generator = RegexGenerator::new(r"\a{x}~", None, None);
+        generator.set_array("x", vec!["a".to_string()]);
+        assert_eq!(generator.generate(), "a");
+    }
+
+    #[cfg(feature = "dictionaries")]
+    #[test]
+    fn test_dictionaries() {
+        let mut generator = RegexGenerator::new(r"\a{@first_name} \a{@last_name} from \a{@city}", None, None);
+        let value = generator.generate();
+        let (name, city) = value.split_once(" from ").unwrap();
+        let (first, last) = name.split_once(' ').unwrap();
+        assert!(advanced_string_generator::dictionary("first_name").unwrap().contains(&first.to_string()));
+        assert!(advanced_string_generator::dictionary("last_name").unwrap().contains(&last.to_string()));
+        assert!(advanced_string_generator::dictionary("city").unwrap().contains(&city.to_string()));
+        assert_eq!(advanced_string_generator::dictionary_names().count(), 7);
+        assert!(advanced_string_generator::dictionary("planet").is_none());
+
+        let mut generator = RegexGenerator::new(r"\a{@country}", None, None);
+        generator.set_array("@country", vec!["Atlantis".to_string()]);
+        assert_eq!(generator.generate(), "Atlantis");
+
+        let mut generator = RegexGenerator::new(r"\a{place}", None, None);
+        generator.set_pattern_array("place", vec![r"in \a{@city}".to_string()]);
+        let value = generator.generate();
+        let city = value.strip_prefix("in ").unwrap();
+        assert!(advanced_string_generator::dictionary("city").unwrap().contains(&city.to_string()), "{}", value);
+    }
+
+    #[test]
+    fn test_tables() {
+        let csv = "city,zip,country\n# comment\nOslo,0150,NO\n\"Washington, D.C.\",20001,US\nLyon,69001,FR\n";
+        let (columns, rows) = parse_table(csv).unwrap();
+        assert_eq!(columns, ["city", "zip", "country"]);
+        assert_eq!(rows[1], ["Washington, D.C.", "20001", "US"]);
+        assert!(parse_table("a,b\n1\n").is_err());
+
+        let mut generator = RegexGenerator::new(r"\a{addr.zip} \a{addr.city}, \a{addr.country}", None, None);
+        generator.set_table("addr", columns.clone(), rows.clone()).unwrap();
+        for _ in 0..20 {
+            let value = generator.generate();
+            assert!(["0150 Oslo, NO", "20001 Washington, D.C., US", "69001 Lyon, FR"].contains(&value.as_str()), "{}", value);
+        }
+        assert!(matches!(generator.enumerate(Order::Shortlex, None), Err(Error::UnsupportedToken(_))));
+
+        let mut generator = RegexGenerator::new(r"\a{addr.city}+/\a{addr.country}/\a{addr.zip}-", None, None);
+        generator.set_table("addr", columns, rows).unwrap();
+        let values: Vec<String> = (0..4).map(|_| generator.generate()).collect();
+        assert_eq!(values, ["Oslo/NO/0150", "Washington, D.C./US/20001", "Lyon/FR/69001", "Oslo/NO/0150"]);
+
+        assert!(generator.set_table("bad", vec!["a".to_string()], vec![vec![]]).is_err());
+        assert!(read_table("addr.csv").is_err());
+    }
+
+    #[test]
+    fn test_pattern_arrays() {
+        assert_eq!(parse_pattern_list(r"ORD-\d{6},INV-[A-Z,]{2}\d{1,4},(a|b,c)\,d"), [r"ORD-\d{6}", r"INV-[A-Z,]{2}\d{1,4}", r"(a|b,c)\,d"]);
+
+        let mut generator = RegexGenerator::new(r"\a", None, None);
+        generator.set_pattern_array("", vec![r"ORD-\d{6}".to_string(), r"INV-[A-Z]{2}\d{4}".to_string()]);
+        for _ in 0..20 {
+            let value = generator.generate();
+            let valid = match value.strip_prefix("ORD-") {
+                Some(digits) => digits.len() == 6 && digits.chars().all(|c| c.is_ascii_digit()),
+                None => value.len() == 10 && value.starts_with("INV-") && value[4..6].chars().all(|c| c.is_ascii_uppercase()),
+            };
+            assert!(valid, "{}", value);
+        }
+        assert!(matches!(generator.enumerate(Order::Shortlex, None), Err(Error::UnsupportedToken(_))));
+
+        let mut generator = RegexGenerator::new(r"(x|y)\a{f}\1", None, None);
+        generator.set_pattern_array("f", vec![r"<\1>(\d)\1".to_string()]);
+        let value = generator.generate();
+        let (outer, digit) = (&value[..1], &value[4..5]);
+        assert_eq!(value, format!("{0}<{0}>{1}{1}{0}", outer, digit));
+
+        let mut generator = RegexGenerator::new(r"\i{n}\a{f}\i{n}", None, None);
+        generator.set_counter("n", Counter::new("0"));
+        generator.set_pattern_array("f", vec![r"-\i{n}-".to_string()]);
+        assert_eq!(generator.generate(), "1-2-3");
+
+        let mut generator = RegexGenerator::new(r"\a{x}", None, None);
+        generator.set_pattern_array("x", vec![r"a\a{x}".to_string()]);
+        assert_eq!(generator.try_generate(), Err(Error::RecursionLimit("x".to_string())));
+        generator.set_array("x", vec![r"a\a{x}".to_string()]);
+        assert_eq!(generator.generate(), r"a\a{x}");
+
+        // The entry's \a+ keeps its own cursor apart from the pattern's.
+        let array_values = Some(vec!["p".to_string(), "q".to_string(), "r".to_string()]);
+        let mut generator = RegexGenerator::new(r"\a+/\a{x}", None, array_values);
+        generator.set_pattern_array("x", vec![r"\a+".to_string()]);
+        let values: Vec<String> = (0..3).map(|_| generator.generate()).collect();
+        assert_eq!(values, ["p/p", "q/q", "r/r"]);
+        let state = generator.state();
+        assert_eq!(state.array_cursors.keys().collect::<Vec<_>>(), ["", "x[0]:"]);
+    }
+
+    #[test]
+    fn test_macros() {
+        let mut generator = RegexGenerator::new(r"\m{ipv4} \m{uuid}", None, None);
+        generator.set_seed(7);
+        for _ in 0..20 {
+            let value = generator.generate();
+            let (address, uuid) = value.split_once(' ').unwrap();
+            let octets: Vec<&str> = address.split('.').collect();
+            assert_eq!(octets.len(), 4, "{}", value);
+            assert!(octets.iter().all(|octet| octet.parse::<u8>().is_ok() && (octet.len() == 1 || !octet.starts_with('0'))), "{}", value);
+            let groups: Vec<usize> = uuid.split('-').map(str::len).collect();
+            assert_eq!(groups, [8, 4, 4, 4, 12], "{}", value);
+            assert_eq!(&uuid[14..15], "4");
+            assert!("89ab".contains(&uuid[19..20]), "{}", value);
+        }
+
+        let mut generator = RegexGenerator::new(r"(?(DEFINE)(?<octet>\d)(?<pair>\m{octet}{2}))<\m{pair}>", None, None);
+        let value = generator.generate();
+        assert_eq!(value.len(), 4, "{}", value);
+        assert!(value[1..3].chars().all(|c| c.is_ascii_digit()));
+
+        let mut generator = RegexGenerator::new(r"\m{x}(\m{ipv4})", None, None);
+        generator.load_library("# pets\n\nx = cat|dog\nipv4 = ip\n").unwrap();
+        let values: Vec<String> = generator.enumerate(Order::Shortlex, None).unwrap().collect();
+        assert_eq!(values, ["catip", "dogip"]);
+        generator.set_macro("x", r"<\m{ipv4}>");
+        assert_eq!(generator.generate(), "<ip>ip");
+
+        let mut generator = RegexGenerator::new(r"\a{x}", None, None);
+        generator.set_pattern_array("x", vec![r"\m{x}".to_string()]);
+        assert_eq!(generator.generate(), "m{x}");
+        generator.set_macro("x", "MAC");
+        assert_eq!(generator.generate(), "MAC");
+
+        let mut generator = RegexGenerator::new(r"(?(DEFINE)(?<x>a\m{x}))\m{x}\m{nope}", None, None);
+        assert_eq!(generator.generate(), "am{x}m{nope}");
+
+        assert!(matches!(parse_library("x = a\n= b"), Err(Error::InvalidMacro(message)) if message.starts_with("line 2")));
+        assert!(parse_library("no pattern").is_err());
+        assert!(parse_library("bad name = a").is_err());
+        assert!(standard_library().any(|(name, _)| name == "semver"));
+    }
+
+    #[test]
+    fn test_identifiers() {
+        let pattern = r"\u{uuid4} \u{uuid7} \u{ulid} \u{nanoid:8} \u{snowflake:5}";
+        let mut generator = RegexGenerator::new(pattern, None, None);
+        generator.set_seed(11);
+        generator.set_clock(Clock::parse("2024-01-01T00:00:00Z").unwrap());
+        let values: Vec<String> = (0..3).map(|_| generator.generate()).collect();
+        let mut again = RegexGenerator::new(pattern, None, None);
+        again.set_seed(11);
+        again.set_clock(Clock::Fixed(1_704_067_200_000));
+        assert_eq!((0..3).map(|_| again.generate()).collect::<Vec<_>>(), values);
+
+        let ids: Vec<Vec<&str>> = values.iter().map(|value| value.split(' ').collect()).collect();
+        for id in &ids {
+            let (uuid4, uuid7) = (id[0], id[1]);
+            assert_eq!(uuid4.split('-').map(str::len).collect::<Vec<_>>(), [8, 4, 4, 4, 12]);
+            assert_eq!(&uuid4[14..15], "4");
+            assert!("89ab".contains(&uuid4[19..20]), "{}", uuid4);
+            assert!(uuid7.starts_with("018cc251-f400-7"), "{}", uuid7);
+            assert!(id[2].starts_with("01HK153X00") && id[2].len() == 26, "{}", id[2]);
+            assert!(id[3].len() == 8 && id[3].chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-'));
+            assert_eq!(id[4].parse::<u64>().unwrap() >> 12 & 1023, 5);
+        }
+        assert!(ids[0][2] < ids[1][2] && ids[1][2] < ids[2][2]);
+        let snowflakes: Vec<u64> = ids.iter().map(|id| id[4].parse().unwrap()).collect();
+        assert_eq!(snowflakes[0] >> 22, 1_704_067_200_000 - 1_288_834_974_657);
+        assert_eq!([snowflakes[1] - snowflakes[0], snowflakes[2] - snowflakes[1]], [1, 1]);
+
+        let mut restored = RegexGenerator::new(r"\u{snowflake}", None, None);
+        restored.set_clock(Clock::Fixed(0));
+        restored.restore_state(generator.state());
+        assert!(restored.generate().parse::<u64>().unwrap() > snowflakes[2]);
+
+        let mut generator = RegexGenerator::new(r"\u{nanoid}\u{bad}\u{nanoid:0}", None, None);
+        let value = generator.generate();
+        assert_eq!(&value[21..], "u{bad}u{nanoid:0}");
+        assert!(matches!(generator.enumerate(Order::Shortlex, None), Err(Error::UnsupportedToken(_))));
+        assert_eq!(Clock::parse("system"), Some(Clock::System));
+        assert_eq!(Clock::parse("1700000000000"), Some(Clock::Fixed(1_700_000_000_000)));
+        assert_eq!(Clock::parse("soon"), None);
+
+        let mut generator = RegexGenerator::new(r"\u{snowflake}", None, None);
+        generator.set_clock(Clock::Fixed(i64::MIN));
+        let snowflakes: Vec<u64> = (0..3).map(|_| generator.generate().parse().unwrap()).collect();
+        assert_eq!(snowflakes, [0, 1, 2]);
+        let mut state = generator.state();
+        state.last_snowflake = Some(u64::MAX >> 1);
+        generator.restore_state(state);
+        assert_eq!(generator.try_generate(), Err(Error::IdentifierOverflow("snowflake".to_string())));
+
+        let mut generator = RegexGenerator::new(r"\u{ulid}", None, None);
+        generator.set_clock(Clock::Fixed(0));
+        let mut state = generator.state();
+        state.last_ulid = Some(u128::MAX);
+        generator.restore_state(state);
+        assert_eq!(generator.try_generate(), Err(Error::IdentifierOverflow("ulid".to_string())));
+    }
+}
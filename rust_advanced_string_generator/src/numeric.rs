@@ -0,0 +1,158 @@
+// The `\r{...}` token: a number from a range, integer or with a fixed
+// number of decimals, drawn uniformly or from a normal distribution.
+
+use rand::Rng;
+
+/// How many draws outside the range a normal distribution may take before
+/// the value is clamped instead.
+const NORMAL_RETRIES: usize = 32;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Shape {
+    Uniform,
+    Normal { mean: f64, sd: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct NumberSpec {
+    /// The text between the braces.
+    pub(crate) source: String,
+    /// Bounds scaled by `10^precision`, so `0.00..999.99` is `0..=99999`.
+    pub(crate) min: i128,
+    pub(crate) max: i128,
+    pub(crate) precision: u32,
+    /// Zero-pad the integer part to this many digits.
+    pub(crate) width: usize,
+    pub(crate) thousands: Option<char>,
+    pub(crate) shape: Shape,
+}
+
+// The `f64`s come from finite parsed numbers, so they are never NaN.
+impl Eq for NumberSpec {}
+
+impl NumberSpec {
+    /// Parses `MIN..MAX[,width=N][,thousands[=SEP]][,uniform|normal]` with
+    /// `mean=X` and `sd=X` to adjust a normal distribution. The precision is
+    /// the larger number of decimals of the two bounds.
+    pub(crate) fn parse(source: &str) -> Option<NumberSpec> {
+        let mut items = source.split(',');
+        let (min, max) = items.next()?.split_once("..")?;
+        let (min, max) = (min.trim(), max.trim());
+        let precision = decimals(min)?.max(decimals(max)?);
+        let (min, max) = (scale(min, precision)?, scale(max, precision)?);
+        if min > max {
+            return None;
+        }
+        let mut spec = NumberSpec {
+            source: source.to_string(),
+            min,
+            max,
+            precision,
+            width: 0,
+            thousands: None,
+            shape: Shape::Uniform,
+        };
+        let (mut normal, mut mean, mut sd) = (false, None, None);
+        for item in items {
+            match item.split_once('=') {
+                None => match item.trim() {
+                    "thousands" => spec.thousands = Some(','),
+                    "uniform" => normal = false,
+                    "normal" => normal = true,
+                    _ => return None,
+                },
+                Some((key, value)) => match key.trim() {
+                    "width" => spec.width = value.trim().parse().ok()?,
+                    // Not trimmed, so `thousands= ` separates with a space.
+                    "thousands" => {
+                        let mut chars = value.chars();
+                        spec.thousands = Some(chars.next()?);
+                        if chars.next().is_some() {
+                            return None;
+                        }
+                    }
+                    "mean" => mean = Some(value.trim().parse::<f64>().ok().filter(|mean| mean.is_finite())?),
+                    "sd" => sd = Some(value.trim().parse::<f64>().ok().filter(|sd| sd.is_finite() && *sd >= 0.0)?),
+                    _ => return None,
+                },
+            }
+        }
+        if normal || mean.is_some() || sd.is_some() {
+            let unit = 10f64.powi(precision as i32);
+            let (low, high) = (spec.min as f64 / unit, spec.max as f64 / unit);
+            spec.shape = Shape::Normal {
+                mean: mean.unwrap_or((low + high) / 2.0),
+                // Six standard deviations span the range by default.
+                sd: sd.unwrap_or((high - low) / 6.0),
+            };
+        }
+        Some(spec)
+    }
+
+    /// How many values the range holds.
+    pub(crate) fn len(&self) -> u128 {
+        self.max.abs_diff(self.min).saturating_add(1)
+    }
+
+    /// A random scaled value in `min..=max`.
+    pub(crate) fn sample<R: Rng>(&self, rng: &mut R) -> i128 {
+        let Shape::Normal { mean, sd } = self.shape else {
+            return rng.gen_range(self.min..=self.max);
+        };
+        let unit = 10f64.powi(self.precision as i32);
+        let mut value = mean;
+        for _ in 0..NORMAL_RETRIES {
+            // Box-Muller transform.
+            let (u, v): (f64, f64) = (1.0 - rng.gen::<f64>(), rng.gen());
+            value = mean + sd * (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos();
+            if (self.min..=self.max).contains(&((value * unit).round() as i128)) {
+                break;
+            }
+        }
+        ((value * unit).round() as i128).clamp(self.min, self.max)
+    }
+
+    pub(crate) fn render(&self, value: i128) -> String {
+        let digits = value.unsigned_abs().to_string();
+        let digits = format!("{:0>width$}", digits, width = self.precision as usize + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - self.precision as usize);
+        let integer = format!("{:0>width$}", integer, width = self.width);
+        let mut result = String::new();
+        if value < 0 {
+            result.push('-');
+        }
+        for (i, ch) in integer.chars().enumerate() {
+            if i > 0 && (integer.len() - i) % 3 == 0 {
+                if let Some(separator) = self.thousands {
+                    result.push(separator);
+                }
+            }
+            result.push(ch);
+        }
+        if !fraction.is_empty() {
+            result.push('.');
+            result.push_str(fraction);
+        }
+        result
+    }
+}
+
+/// Number of digits after the decimal point, or `None` if `text` is not a
+/// plain decimal number.
+fn decimals(text: &str) -> Option<u32> {
+    let unsigned = text.strip_prefix('-').unwrap_or(text);
+    let (integer, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+    let is_digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+    if integer.is_empty() || !is_digits(integer) || !is_digits(fraction) || (unsigned.contains('.') && fraction.is_empty()) {
+        return None;
+    }
+    Some(fraction.len() as u32)
+}
+
+/// The number times `10^precision`, exactly.
+fn scale(text: &str, precision: u32) -> Option<i128> {
+    let (integer, fraction) = text.split_once('.').unwrap_or((text, ""));
+    let fraction = format!("{:0<width$}", fraction, width = precision as usize);
+    let magnitude: i128 = format!("{}{}", integer.trim_start_matches('-'), fraction).parse().ok()?;
+    Some(if text.starts_with('-') { -magnitude } else { magnitude })
+}
@@ -0,0 +1,139 @@
+// All-pairs planning over the choice points of a pattern: alternations with
+// more than one branch and random `\a` arrays. Each planned test case fixes
+// a choice for some of those nodes, and together the cases contain every
+// feasible pair of choices at least once. Choice points inside repeats are
+// left random, since they may run any number of times.
+
+use crate::parser::{ArrayOrder, Node};
+use crate::regex_generator::Arrays;
+use std::collections::{BTreeSet, HashMap};
+
+struct Parameter {
+    node: usize,
+    values: usize,
+    /// The branches of enclosing alternations that must be taken for this
+    /// choice point to be reached, as (parameter, value) pairs.
+    condition: Vec<(usize, usize)>,
+}
+
+type Pair = ((usize, usize), (usize, usize));
+
+fn collect(node: &Node, arrays: &Arrays, condition: &mut Vec<(usize, usize)>, parameters: &mut Vec<Parameter>) {
+    match node {
+        Node::Group { alternatives, .. } if alternatives.len() > 1 => {
+            let parameter = parameters.len();
+            parameters.push(Parameter { node: node.id(), values: alternatives.len(), condition: condition.clone() });
+            for (branch, alternative) in alternatives.iter().enumerate() {
+                condition.push((parameter, branch));
+                for node in alternative {
+                    collect(node, arrays, condition, parameters);
+                }
+                condition.pop();
+            }
+        }
+        Node::Group { alternatives, .. } => {
+            for node in alternatives.iter().flatten() {
+                collect(node, arrays, condition, parameters);
+            }
+        }
+        Node::Array { name, order: ArrayOrder::Random, .. } => {
+            let values = arrays.get(name).map_or(0, Vec::len);
+            if values > 1 {
+                parameters.push(Parameter { node: node.id(), values, condition: condition.clone() });
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Plans the test cases, each a map from node id to the branch or array
+/// index to use.
+pub(crate) fn plan(root: &Node, arrays: &Arrays) -> Vec<HashMap<usize, usize>> {
+    let mut parameters = Vec::new();
+    collect(root, arrays, &mut Vec::new(), &mut parameters);
+
+    let requirements = |parameter: usize, value: usize| {
+        let mut required = parameters[parameter].condition.clone();
+        required.push((parameter, value));
+        required
+    };
+    let mut uncovered: BTreeSet<Pair> = BTreeSet::new();
+    for p in 0..parameters.len() {
+        for q in p + 1..parameters.len() {
+            for a in 0..parameters[p].values {
+                for b in 0..parameters[q].values {
+                    let mut assignment = HashMap::new();
+                    if assign_all(&mut assignment, &requirements(p, a)) && assign_all(&mut assignment, &requirements(q, b)) {
+                        uncovered.insert(((p, a), (q, b)));
+                    }
+                }
+            }
+        }
+    }
+
+    let mut cases = Vec::new();
+    while let Some(&((p, a), (q, b))) = uncovered.iter().next() {
+        let mut assignment: HashMap<usize, usize> = HashMap::new();
+        assign_all(&mut assignment, &requirements(p, a));
+        assign_all(&mut assignment, &requirements(q, b));
+
+        // Parameters come in pre-order, so enclosing alternations are
+        // settled before the choice points inside them.
+        for (r, parameter) in parameters.iter().enumerate() {
+            let active = parameter.condition.iter().all(|(s, value)| assignment.get(s) == Some(value));
+            if assignment.contains_key(&r) || !active {
+                continue;
+            }
+            let score = |v: usize| {
+                assignment
+                    .iter()
+                    .filter(|&(&s, &w)| {
+                        let pair = if s < r { ((s, w), (r, v)) } else { ((r, v), (s, w)) };
+                        uncovered.contains(&pair)
+                    })
+                    .count()
+            };
+            let best = (0..parameter.values).max_by_key(|&v| (score(v), std::cmp::Reverse(v))).unwrap();
+            assignment.insert(r, best);
+        }
+
+        for (&s, &v) in &assignment {
+            for (&t, &w) in &assignment {
+                if s < t {
+                    uncovered.remove(&((s, v), (t, w)));
+                }
+            }
+        }
+        cases.push(assignment);
+    }
+
+    // With fewer than two reachable choice points there are no pairs, but
+    // every single choice should still show up.
+    for (p, parameter) in parameters.iter().enumerate() {
+        for a in 0..parameter.values {
+            if !cases.iter().any(|case| case.get(&p) == Some(&a)) {
+                let mut assignment = HashMap::new();
+                assign_all(&mut assignment, &requirements(p, a));
+                cases.push(assignment);
+            }
+        }
+    }
+    if cases.is_empty() {
+        cases.push(HashMap::new());
+    }
+
+    cases
+        .into_iter()
+        .map(|case| case.into_iter().map(|(r, value)| (parameters[r].node, value)).collect())
+        .collect()
+}
+
+/// Adds `required` to `assignment`, returning false on a conflict.
+fn assign_all(assignment: &mut HashMap<usize, usize>, required: &[(usize, usize)]) -> bool {
+    for &(parameter, value) in required {
+        if *assignment.entry(parameter).or_insert(value) != value {
+            return false;
+        }
+    }
+    true
+}
@@ -0,0 +1,768 @@
+// Turns a pattern string into a tree of nodes that the generator and the
+// enumeration code walk. The parser is lenient: anything it does not
+// recognise is kept as literal text, the same way the generator always
+// treated unknown input.
+
+use crate::alias::{self, AliasTable};
+use crate::counter::{self, CounterOptions};
+use crate::identifier::IdSpec;
+use crate::library::{self, Macros};
+use crate::numeric::NumberSpec;
+use crate::timestamp::TimeSpec;
+use std::collections::HashMap;
+use std::fmt;
+
+pub(crate) const WORD_CHARS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789_";
+pub(crate) const SPACE_CHARS: &str = " \t\n\r";
+pub(crate) const NON_DIGIT_CHARS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz!@#$%^&*()";
+pub(crate) const NON_WORD_CHARS: &str = "!@#$%^&*()+=-[]{}|;:,.<>?/`~";
+pub(crate) const NON_SPACE_CHARS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789!@#$%^&*()";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ArrayOrder {
+    Random,
+    Ascending,
+    Descending,
+    /// `\a~`: each value once per pass, in an order shuffled for every pass.
+    Deck,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Node {
+    Literal(char),
+    /// One character out of a sorted, de-duplicated set.
+    Class(Vec<char>),
+    /// A parenthesised group (or the whole pattern when `index` is `None`).
+    /// `weights` is set when a branch starts with a `<weight>` prefix.
+    Group { index: Option<usize>, alternatives: Vec<Vec<Node>>, weights: Option<Box<AliasTable>> },
+    /// `max` is `None` for unbounded repeats such as `*` or `{2,}`.
+    Repeat { node: Box<Node>, min: usize, max: Option<usize> },
+    /// `{n:m}`: an n-digit number left padded with zeros to m characters.
+    PaddedNumber { digits: usize, width: usize },
+    Backreference(usize),
+    /// `name` is empty for the default counter; `options` holds the
+    /// settings given in the pattern, which override the counter's own.
+    Increment { name: String, options: CounterOptions },
+    /// `\I`: the current value of a counter, which is not advanced.
+    Current { name: String },
+    /// `\T{...}`: a timestamp from a sequence or a random one.
+    Timestamp(Box<TimeSpec>),
+    /// `\r{...}`: a random number from a range.
+    Number(Box<NumberSpec>),
+    /// `\u{...}`: a UUID, ULID, Nano ID or Snowflake ID.
+    Identifier(IdSpec),
+    /// `\a`, or `\a{name}` for a named array; `name` is empty for the
+    /// unnamed one. `cursor` keys the position of an ordered occurrence:
+    /// the name for the first one, `name#2`, `name#3`... for later ones,
+    /// and `name@shared` for those given `cursor=shared`.
+    Array { name: String, order: ArrayOrder, cursor: String },
+}
+
+impl Node {
+    /// The options of the first `\i` of the counter `name` in the tree.
+    pub(crate) fn counter_options(&self, name: &str) -> Option<&CounterOptions> {
+        match self {
+            Node::Group { alternatives, .. } => alternatives.iter().flatten().find_map(|node| node.counter_options(name)),
+            Node::Repeat { node, .. } => node.counter_options(name),
+            Node::Increment { name: counter, options } if counter == name => Some(options),
+            _ => None,
+        }
+    }
+
+    /// Calls `f` with the name of every `\a` in the tree.
+    pub(crate) fn for_each_array(&self, f: &mut impl FnMut(&str)) {
+        match self {
+            Node::Group { alternatives, .. } => alternatives.iter().flatten().for_each(|node| node.for_each_array(f)),
+            Node::Repeat { node, .. } => node.for_each_array(f),
+            Node::Array { name, .. } => f(name),
+            _ => {}
+        }
+    }
+
+    /// Identifies a node by its address; the tree is never changed after
+    /// parsing, so this is stable until the generator parses its pattern
+    /// again for new macros.
+    pub(crate) fn id(&self) -> usize {
+        self as *const Node as usize
+    }
+}
+
+/// Parses a pattern. `\m{name}` finds its definition in the pattern's own
+/// `(?(DEFINE)...)` blocks first, then in `macros`, then in the standard
+/// library.
+pub(crate) fn parse(pattern: &str, macros: &Macros) -> Node {
+    parse_entry(pattern, macros, "")
+}
+
+/// Parses an entry of a pattern array. Its ordered `\a` occurrences keep
+/// their cursors under `scope`, such as `list[0]`, apart from those of the
+/// main pattern and of the other entries.
+pub(crate) fn parse_entry(pattern: &str, macros: &Macros, scope: &str) -> Node {
+    let mut chars: Vec<char> = pattern.chars().collect();
+    let mut all = library::standard_macros();
+    all.extend(macros.iter().map(|(name, body)| (name.clone(), body.clone())));
+    all.extend(take_definitions(&mut chars));
+    let mut parser = Parser {
+        chars,
+        pos: 0,
+        group_count: 0,
+        array_occurrences: HashMap::new(),
+        scope,
+        macros: &all,
+        expanding: Vec::new(),
+    };
+    parser.parse_all()
+}
+
+/// A group whose branches without a weight weigh 1. When no branch has a
+/// weight, or all weights are zero, the branches are equally likely.
+fn group(index: Option<usize>, alternatives: Vec<Vec<Node>>, weights: Vec<Option<f64>>) -> Node {
+    let weights = match weights.iter().any(Option::is_some) {
+        true => AliasTable::new(weights.into_iter().map(|weight| weight.unwrap_or(1.0)).collect()).map(Box::new),
+        false => None,
+    };
+    Node::Group { index, alternatives, weights }
+}
+
+struct Parser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    group_count: usize,
+    array_occurrences: HashMap<String, usize>, // Ordered \a occurrences seen per array
+    scope: &'a str, // Prefix of the cursors of ordered \a occurrences
+    macros: &'a Macros,
+    expanding: Vec<String>, // Macros being expanded, which may not use themselves
+}
+
+impl Parser<'_> {
+    /// Parses everything left as the alternatives of one group. Only branches
+    /// inside parentheses take weights, so a top-level `<3>x` stays literal.
+    fn parse_all(&mut self) -> Node {
+        let (mut alternatives, mut weights) = self.parse_alternatives(false);
+        // A stray ')' ends the alternatives early; keep the rest as literal text.
+        while self.pos < self.chars.len() {
+            self.pos += 1;
+            let mut rest = vec![Node::Literal(')')];
+            let (mut tail, tail_weights) = self.parse_alternatives(false);
+            rest.append(&mut tail[0]);
+            alternatives.last_mut().unwrap().append(&mut rest);
+            alternatives.extend(tail.into_iter().skip(1));
+            weights.extend(tail_weights.into_iter().skip(1));
+        }
+        group(None, alternatives, weights)
+    }
+
+    /// Parses the definition of a macro as if `(?:body)` stood in its place,
+    /// so its capturing groups are numbered along with the pattern's.
+    fn expand(&mut self, name: &str, body: &str) -> Node {
+        let mut parser = Parser {
+            chars: body.chars().collect(),
+            pos: 0,
+            group_count: self.group_count,
+            array_occurrences: std::mem::take(&mut self.array_occurrences),
+            scope: self.scope,
+            macros: self.macros,
+            expanding: self.expanding.iter().cloned().chain([name.to_string()]).collect(),
+        };
+        let node = parser.parse_all();
+        self.group_count = parser.group_count;
+        self.array_occurrences = parser.array_occurrences;
+        node
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let ch = self.peek();
+        if ch.is_some() {
+            self.pos += 1;
+        }
+        ch
+    }
+
+    /// Parses branches up to a `)`, with the weight of each when `weighted`.
+    fn parse_alternatives(&mut self, weighted: bool) -> (Vec<Vec<Node>>, Vec<Option<f64>>) {
+        let mut alternatives = vec![Vec::new()];
+        let mut weights = vec![if weighted { self.parse_weight() } else { None }];
+        while let Some(ch) = self.peek() {
+            match ch {
+                ')' => break,
+                '|' => {
+                    self.pos += 1;
+                    alternatives.push(Vec::new());
+                    weights.push(if weighted { self.parse_weight() } else { None });
+                }
+                _ => {
+                    let node = self.parse_atom();
+                    let node = self.parse_quantifier(node);
+                    alternatives.last_mut().unwrap().push(node);
+                }
+            }
+        }
+        (alternatives, weights)
+    }
+
+    /// A `<weight>` prefix at the start of a branch, as in `(<80>paid|<20>due)`.
+    fn parse_weight(&mut self) -> Option<f64> {
+        if self.peek() != Some('<') {
+            return None;
+        }
+        let end = self.pos + self.chars[self.pos..].iter().position(|&c| c == '>')?;
+        let text: String = self.chars[self.pos + 1..end].iter().collect();
+        let weight = alias::parse_weight(&text)?;
+        self.pos = end + 1;
+        Some(weight)
+    }
+
+    fn parse_atom(&mut self) -> Node {
+        match self.next().unwrap() {
+            '\\' => self.parse_escape(),
+            '[' => self.parse_class(),
+            '(' => {
+                let index = if self.peek() == Some('?') && self.chars.get(self.pos + 1) == Some(&':') {
+                    self.pos += 2;
+                    None
+                } else {
+                    self.group_count += 1;
+                    Some(self.group_count)
+                };
+                let (alternatives, weights) = self.parse_alternatives(true);
+                self.next(); // Skip the ')'
+                group(index, alternatives, weights)
+            }
+            ch => Node::Literal(ch),
+        }
+    }
+
+    fn parse_escape(&mut self) -> Node {
+        let Some(ch) = self.next() else {
+            return Node::Literal('\\');
+        };
+        match ch {
+            'i' => {
+                let start = self.pos;
+                let mut options = CounterOptions { direction: self.parse_direction(), ..Default::default() };
+                // Counter name, leading zero specifier and options {name:total_len,step=N,...}
+                let braces = self.pos;
+                let mut name = String::new();
+                let mut valid = true;
+                if self.peek() == Some('{') {
+                    let spec = self.take_braces().unwrap_or_default();
+                    for (i, item) in spec.split(',').enumerate() {
+                        if i > 0 || item.contains('=') {
+                            valid &= options.apply(item, false);
+                            continue;
+                        }
+                        let (item_name, width) = match item.split_once(':') {
+                            Some((item_name, width)) => (item_name.trim(), width),
+                            None if counter::is_valid_name(item.trim()) => (item.trim(), ""),
+                            None => ("", item),
+                        };
+                        let digits: String = width.chars().filter(|c| c.is_ascii_digit()).collect();
+                        name = item_name.to_string();
+                        options.width = digits.parse().ok();
+                    }
+                }
+                // An unknown or inconsistent option leaves `i`, the direction
+                // and the braces to be read as literals.
+                if !valid || !options.is_consistent() {
+                    self.pos = braces;
+                    let literals = self.chars[start - 1..braces].iter().map(|&ch| Node::Literal(ch)).collect();
+                    return group(None, vec![literals], vec![None]);
+                }
+                Node::Increment { name, options }
+            }
+            'I' => {
+                let name = if self.peek() == Some('{') { self.take_braces().unwrap_or_default() } else { String::new() };
+                Node::Current { name: name.trim().to_string() }
+            }
+            'T' => {
+                // An invalid spec leaves the braces to be read as literals.
+                let start = self.pos;
+                let spec = if self.peek() == Some('{') { self.take_braces() } else { None };
+                match spec.as_deref().and_then(TimeSpec::parse) {
+                    Some(spec) => Node::Timestamp(Box::new(spec)),
+                    None => {
+                        self.pos = start;
+                        Node::Literal('T')
+                    }
+                }
+            }
+            'u' => {
+                let start = self.pos;
+                let spec = if self.peek() == Some('{') { self.take_braces() } else { None };
+                match spec.as_deref().and_then(IdSpec::parse) {
+                    Some(spec) => Node::Identifier(spec),
+                    None => {
+                        self.pos = start;
+                        Node::Literal('u')
+                    }
+                }
+            }
+            'm' => {
+                // An unknown macro, or one used inside its own definition,
+                // leaves the braces to be read as literals.
+                let start = self.pos;
+                let name = if self.peek() == Some('{') { self.take_braces() } else { None };
+                let macros = self.macros;
+                let definition = name
+                    .map(|name| name.trim().to_string())
+                    .filter(|name| !self.expanding.contains(name))
+                    .and_then(|name| Some((macros.get(&name)?, name)));
+                match definition {
+                    Some((body, name)) => self.expand(&name, body),
+                    None => {
+                        self.pos = start;
+                        Node::Literal('m')
+                    }
+                }
+            }
+            'r' => {
+                let start = self.pos;
+                let spec = if self.peek() == Some('{') { self.take_braces() } else { None };
+                match spec.as_deref().and_then(NumberSpec::parse) {
+                    Some(spec) => Node::Number(Box::new(spec)),
+                    None => {
+                        self.pos = start;
+                        Node::Literal('r')
+                    }
+                }
+            }
+            'a' => {
+                // The order goes before or after the name: \a+{city} or \a{city}+.
+                let mut order = self.parse_array_order();
+                let (name, shared) = self.parse_array_spec();
+                if order == ArrayOrder::Random {
+                    order = self.parse_array_order();
+                }
+                let cursor = match shared {
+                    Some(shared) => format!("{}@{}", name, shared),
+                    None if order == ArrayOrder::Random => name.clone(),
+                    None => {
+                        let occurrence = self.array_occurrences.entry(name.clone()).or_insert(0);
+                        *occurrence += 1;
+                        let key = if self.scope.is_empty() { name.clone() } else { format!("{}:{}", self.scope, name) };
+                        if *occurrence == 1 { key } else { format!("{}#{}", key, occurrence) }
+                    }
+                };
+                Node::Array { name, order, cursor }
+            }
+            '1'..='9' => Node::Backreference(ch.to_digit(10).unwrap() as usize),
+            _ => escape_class(ch).map(Node::Class).unwrap_or_else(|| Node::Literal(escape_literal(ch))),
+        }
+    }
+
+    /// The `{name}` of a named array, `{@name}` of a bundled dictionary or
+    /// `{table.column}`, with the name of a shared cursor from
+    /// `{name,cursor=NAME}` or `{cursor=NAME}`. Other braces are left for the
+    /// quantifier, as in `\a{3}`.
+    fn parse_array_spec(&mut self) -> (String, Option<String>) {
+        let start = self.pos;
+        if self.peek() == Some('{') {
+            if let Some(spec) = self.take_braces().as_deref().and_then(parse_array_spec) {
+                return spec;
+            }
+            self.pos = start;
+        }
+        (String::new(), None)
+    }
+
+    fn parse_array_order(&mut self) -> ArrayOrder {
+        let order = match self.peek() {
+            Some('+') => ArrayOrder::Ascending,
+            Some('-') => ArrayOrder::Descending,
+            Some('~') => ArrayOrder::Deck,
+            _ => return ArrayOrder::Random,
+        };
+        self.pos += 1;
+        order
+    }
+
+    fn parse_direction(&mut self) -> Option<i32> {
+        match self.peek() {
+            Some('+') => {
+                self.pos += 1;
+                Some(1)
+            }
+            Some('-') => {
+                self.pos += 1;
+                Some(-1)
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_class(&mut self) -> Node {
+        let mut char_class = Vec::new();
+        let mut negate = false;
+        let mut range_start: Option<char> = None;
+
+        if self.peek() == Some('^') {
+            self.pos += 1;
+            negate = true;
+        }
+
+        while let Some(ch) = self.next() {
+            if ch == ']' {
+                break;
+            } else if ch == '-' && range_start.is_some() && self.peek().is_some_and(|c| c != ']') {
+                let range_end = match self.next().unwrap() {
+                    '\\' => self.next().map(escape_literal).unwrap_or('\\'),
+                    c => c,
+                };
+                char_class.extend(range_start.unwrap()..=range_end);
+                range_start = None;
+            } else if ch == '\\' {
+                let Some(escaped) = self.next() else { break };
+                if let Some(set) = escape_class(escaped) {
+                    char_class.extend(set);
+                    range_start = None;
+                } else {
+                    let literal = escape_literal(escaped);
+                    char_class.push(literal);
+                    range_start = Some(literal);
+                }
+            } else {
+                char_class.push(ch);
+                range_start = Some(ch);
+            }
+        }
+
+        if negate {
+            char_class = (32u8..127).map(char::from).filter(|c| !char_class.contains(c)).collect();
+        }
+        char_class.sort_unstable();
+        char_class.dedup();
+        Node::Class(char_class)
+    }
+
+    fn parse_quantifier(&mut self, node: Node) -> Node {
+        // Counters carry their own {...} specifier and take no quantifier.
+        if matches!(node, Node::Increment { .. } | Node::Current { .. } | Node::Timestamp(_) | Node::Number(_) | Node::Identifier(_)) {
+            return node;
+        }
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => {
+                let start = self.pos;
+                let Some(spec) = self.take_braces() else {
+                    return node;
+                };
+                match parse_repeat_spec(&spec) {
+                    Some(RepeatSpec::Range(min, max)) => return repeat(node, min, max),
+                    Some(RepeatSpec::Padded(digits, width)) => {
+                        return Node::PaddedNumber { digits: digits.max(1), width };
+                    }
+                    None => {
+                        // Not a quantifier; leave the braces as literal text.
+                        self.pos = start;
+                        return node;
+                    }
+                }
+            }
+            _ => return node,
+        };
+        self.pos += 1;
+        repeat(node, min, max)
+    }
+
+    /// Consumes `{...}` and returns its contents, or `None` if it is unclosed.
+    fn take_braces(&mut self) -> Option<String> {
+        let close = self.chars[self.pos..].iter().position(|&c| c == '}')?;
+        let spec = self.chars[self.pos + 1..self.pos + close].iter().collect();
+        self.pos += close + 1;
+        Some(spec)
+    }
+}
+
+fn parse_array_spec(spec: &str) -> Option<(String, Option<String>)> {
+    let mut name = String::new();
+    let mut shared = None;
+    for (i, item) in spec.split(',').map(str::trim).enumerate() {
+        match item.split_once('=') {
+            None if i == 0 && is_array_name(item) => name = item.to_string(),
+            Some((key, value)) if key.trim() == "cursor" && counter::is_valid_name(value.trim()) => {
+                shared = Some(value.trim().to_string())
+            }
+            _ => return None,
+        }
+    }
+    Some((name, shared))
+}
+
+/// Removes the `(?(DEFINE)(?<name>body)...)` blocks from a pattern and
+/// returns their definitions. A block that is not made only of named groups
+/// is left in place.
+fn take_definitions(chars: &mut Vec<char>) -> Vec<(String, String)> {
+    const DEFINE: &str = "(?(DEFINE)";
+    let mut definitions = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            '[' => i = class_end(chars, i),
+            '(' if chars[i..].iter().take(DEFINE.len()).copied().eq(DEFINE.chars()) => {
+                let Some(end) = matching_paren(chars, i) else { break };
+                match named_groups(chars, i + DEFINE.len(), end) {
+                    Some(found) => {
+                        definitions.extend(found);
+                        chars.drain(i..=end);
+                    }
+                    None => i += 1,
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    definitions
+}
+
+/// Reads `(?<name>body)` groups filling `chars[start..end]` exactly.
+fn named_groups(chars: &[char], mut start: usize, end: usize) -> Option<Vec<(String, String)>> {
+    let mut groups = Vec::new();
+    while start < end {
+        if !chars[start..].starts_with(&['(', '?', '<']) {
+            return None;
+        }
+        let close = matching_paren(chars, start).filter(|&close| close < end)?;
+        let name_end = start + 3 + chars[start + 3..close].iter().position(|&c| c == '>')?;
+        let name: String = chars[start + 3..name_end].iter().collect();
+        if !counter::is_valid_name(&name) {
+            return None;
+        }
+        groups.push((name, chars[name_end + 1..close].iter().collect()));
+        start = close + 1;
+    }
+    Some(groups)
+}
+
+/// The index of the `)` closing the `(` at `open`, skipping escapes and
+/// character classes.
+fn matching_paren(chars: &[char], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = open;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '[' => {
+                i = class_end(chars, i);
+                continue;
+            }
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// The index after the `]` closing the class that starts at `open`.
+fn class_end(chars: &[char], open: usize) -> usize {
+    let mut i = open + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            ']' => return i + 1,
+            _ => i += 1,
+        }
+    }
+    chars.len()
+}
+
+/// A name as in `city`, `@city` for a bundled dictionary, or `addr.city` for
+/// a column of a table.
+fn is_array_name(name: &str) -> bool {
+    let name = name.strip_prefix('@').unwrap_or(name);
+    match name.split_once('.') {
+        Some((table, column)) => counter::is_valid_name(table) && counter::is_valid_name(column),
+        None => counter::is_valid_name(name),
+    }
+}
+
+enum RepeatSpec {
+    Range(usize, Option<usize>),
+    Padded(usize, usize),
+}
+
+fn parse_repeat_spec(spec: &str) -> Option<RepeatSpec> {
+    if let Some((num_len, total_len)) = spec.split_once(':') {
+        // Handle leading zeros pattern {num_len:total_len}
+        return Some(RepeatSpec::Padded(num_len.trim().parse().ok()?, total_len.trim().parse().ok()?));
+    }
+    // Handle regular repeat pattern {min}, {min,max} and {min,}
+    match spec.split_once(',') {
+        None => {
+            let count = spec.trim().parse().ok()?;
+            Some(RepeatSpec::Range(count, Some(count)))
+        }
+        Some((min, max)) => {
+            let min = min.trim().parse().ok()?;
+            let max = if max.trim().is_empty() { None } else { Some(max.trim().parse().ok()?) };
+            Some(RepeatSpec::Range(min, max))
+        }
+    }
+}
+
+fn repeat(node: Node, min: usize, max: Option<usize>) -> Node {
+    let max = max.map(|max| max.max(min));
+    Node::Repeat { node: Box::new(node), min, max }
+}
+
+fn escape_class(ch: char) -> Option<Vec<char>> {
+    let sample_set = match ch {
+        'd' => "0123456789",
+        'w' => WORD_CHARS,
+        's' => SPACE_CHARS,
+        'D' => NON_DIGIT_CHARS,
+        'W' => NON_WORD_CHARS,
+        'S' => NON_SPACE_CHARS,
+        _ => return None,
+    };
+    let mut set: Vec<char> = sample_set.chars().collect();
+    set.sort_unstable();
+    Some(set)
+}
+
+fn escape_literal(ch: char) -> char {
+    match ch {
+        't' => '\t', // \t - Tab character
+        'n' => '\n', // \n - Line feed character
+        _ => ch,
+    }
+}
+
+/// Writes a node back as pattern text, used when reporting on parts of a
+/// pattern. The output parses to the same node but may be spelled
+/// differently from the original (`[a-c]` for `[abc]`, for example).
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Node::Literal(ch) => write_char(f, *ch),
+            Node::Class(set) => {
+                if let Some(name) = "dwsDWS".chars().find(|&name| escape_class(name).as_ref() == Some(set)) {
+                    return write!(f, "\\{}", name);
+                }
+                f.write_str("[")?;
+                let mut i = 0;
+                while i < set.len() {
+                    let mut end = i;
+                    while end + 1 < set.len() && set[end] as u32 + 1 == set[end + 1] as u32 {
+                        end += 1;
+                    }
+                    write_class_char(f, set[i])?;
+                    if end >= i + 2 {
+                        f.write_str("-")?;
+                    }
+                    if end > i {
+                        write_class_char(f, set[end])?;
+                    }
+                    i = end + 1;
+                }
+                f.write_str("]")
+            }
+            Node::Group { index, alternatives, weights } => {
+                f.write_str(if index.is_some() { "(" } else { "(?:" })?;
+                write_alternatives(f, alternatives, weights.as_deref())?;
+                f.write_str(")")
+            }
+            Node::Repeat { node, min, max } => {
+                write!(f, "{}", node)?;
+                match (min, max) {
+                    (0, None) => f.write_str("*"),
+                    (1, None) => f.write_str("+"),
+                    (0, Some(1)) => f.write_str("?"),
+                    (min, None) => write!(f, "{{{},}}", min),
+                    (min, Some(max)) if min == max => write!(f, "{{{}}}", min),
+                    (min, Some(max)) => write!(f, "{{{},{}}}", min, max),
+                }
+            }
+            Node::PaddedNumber { digits, width } => write!(f, "\\d{{{}:{}}}", digits, width),
+            Node::Backreference(index) => write!(f, "\\{}", index),
+            Node::Increment { name, options } => {
+                f.write_str(match options.direction {
+                    Some(1) => "\\i+",
+                    Some(_) => "\\i-",
+                    None => "\\i",
+                })?;
+                let mut spec = match options.width {
+                    Some(width) => format!("{}:{}", name, width),
+                    None => name.clone(),
+                };
+                spec.push_str(&options.to_string());
+                match spec.strip_prefix(',') {
+                    Some(spec) => write!(f, "{{{}}}", spec),
+                    None if !spec.is_empty() => write!(f, "{{{}}}", spec),
+                    None => Ok(()),
+                }
+            }
+            Node::Current { name } if name.is_empty() => f.write_str("\\I"),
+            Node::Current { name } => write!(f, "\\I{{{}}}", name),
+            Node::Timestamp(spec) => write!(f, "\\T{{{}}}", spec.source),
+            Node::Number(spec) => write!(f, "\\r{{{}}}", spec.source),
+            Node::Identifier(spec) => write!(f, "\\u{{{}}}", spec),
+            Node::Array { name, order, cursor } => {
+                f.write_str("\\a")?;
+                match cursor.strip_prefix(name.as_str()).and_then(|rest| rest.strip_prefix('@')) {
+                    Some(shared) if name.is_empty() => write!(f, "{{cursor={}}}", shared)?,
+                    Some(shared) => write!(f, "{{{},cursor={}}}", name, shared)?,
+                    None if !name.is_empty() => write!(f, "{{{}}}", name)?,
+                    None => {}
+                }
+                match order {
+                    ArrayOrder::Random => Ok(()),
+                    ArrayOrder::Ascending => f.write_str("+"),
+                    ArrayOrder::Descending => f.write_str("-"),
+                    ArrayOrder::Deck => f.write_str("~"),
+                }
+            }
+        }
+    }
+}
+
+/// Writes alternatives separated by `|`, as in a group or the whole pattern,
+/// each with its weight when the group has them.
+pub(crate) fn write_alternatives(f: &mut impl fmt::Write, alternatives: &[Vec<Node>], weights: Option<&AliasTable>) -> fmt::Result {
+    for (i, alternative) in alternatives.iter().enumerate() {
+        if i > 0 {
+            f.write_str("|")?;
+        }
+        match weights {
+            Some(table) => write!(f, "<{}>", table.weights[i])?,
+            // Keep a leading `<` from reading as a weight.
+            None if alternative.first() == Some(&Node::Literal('<')) => f.write_str("\\")?,
+            None => {}
+        }
+        for node in alternative {
+            write!(f, "{}", node)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_char(f: &mut fmt::Formatter<'_>, ch: char) -> fmt::Result {
+    match ch {
+        '\t' => f.write_str("\\t"),
+        '\n' => f.write_str("\\n"),
+        '\\' | '(' | ')' | '[' | ']' | '{' | '}' | '|' | '*' | '+' | '?' => write!(f, "\\{}", ch),
+        _ => write!(f, "{}", ch),
+    }
+}
+
+fn write_class_char(f: &mut fmt::Formatter<'_>, ch: char) -> fmt::Result {
+    match ch {
+        '\t' => f.write_str("\\t"),
+        '\n' => f.write_str("\\n"),
+        '\\' | ']' | '^' | '-' => write!(f, "\\{}", ch),
+        _ => write!(f, "{}", ch),
+    }
+}
@@ -0,0 +1,66 @@
+// A keyed pseudo-random permutation of `0..domain`, used to hand out every
+// index exactly once in a shuffled order without remembering which ones were
+// already used. It is a balanced Feistel network over the smallest even bit
+// width that covers the domain, with cycle walking to stay inside it.
+
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+const ROUNDS: u64 = 6;
+
+pub(crate) struct Permutation {
+    domain: u128,
+    half_bits: u32,
+    key: u64,
+}
+
+impl Permutation {
+    pub(crate) fn new(domain: u128, key: u64) -> Self {
+        let bits = 128 - domain.saturating_sub(1).leading_zeros();
+        Self { domain, half_bits: bits.div_ceil(2).max(1), key }
+    }
+
+    /// Maps `index` (which must be below the domain) to its shuffled position.
+    pub(crate) fn apply(&self, index: u128) -> u128 {
+        let mut value = self.encrypt(index);
+        // Each step stays in the Feistel domain, which is less than four times
+        // larger than ours, so this loop ends after a few rounds on average.
+        while value >= self.domain {
+            value = self.encrypt(value);
+        }
+        value
+    }
+
+    fn encrypt(&self, value: u128) -> u128 {
+        let mask = (1u128 << self.half_bits) - 1;
+        let mut left = (value >> self.half_bits) & mask;
+        let mut right = value & mask;
+        for round in 0..ROUNDS {
+            let mixed = self.round(right as u64, round) as u128
+                | ((self.round((right >> 64) as u64, round + ROUNDS) as u128) << 64);
+            (left, right) = (right, (left ^ mixed) & mask);
+        }
+        (left << self.half_bits) | right
+    }
+
+    fn round(&self, value: u64, round: u64) -> u64 {
+        // splitmix64 finaliser over the value, key and round number
+        let mut z = value ^ self.key.rotate_left(round as u32 * 11) ^ round.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+/// The order of one pass through a deck of `len` values: a shuffle seeded
+/// from the key, the cursor and the pass number, so a restored generator
+/// deals the same cards without saving them.
+pub(crate) fn deck(len: usize, key: u64, cursor: &str, pass: usize) -> Vec<usize> {
+    // FNV-1a, which unlike the standard hasher is stable between releases.
+    let hash = cursor.bytes().fold(0xCBF2_9CE4_8422_2325u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100_0000_01B3));
+    let seed = key ^ hash ^ (pass as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    let mut order: Vec<usize> = (0..len).collect();
+    order.shuffle(&mut SmallRng::seed_from_u64(seed));
+    order
+}
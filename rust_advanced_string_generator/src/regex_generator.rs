@@ -1,354 +1,758 @@
-use rand::Rng;
-use std::collections::HashMap;
-use std::collections::HashSet;
+use crate::alias::AliasTable;
+use crate::counter::{Counter, CounterMode, CounterOptions};
+use crate::coverage::{Coverage, CoverageWalk, Goal};
+use crate::dictionary;
+use crate::distribution::{self, Distribution};
+use crate::error::Error;
+use crate::identifier::{self, Clock, IdSpec};
+use crate::language::{Enumerate, Language, Order};
+use crate::library::{self, Macros};
+use crate::parser::{self, ArrayOrder, Node};
+use crate::pairwise;
+use crate::permutation::{self, Permutation};
+use crate::state::GeneratorState;
+use crate::strategy::{self, Strategy};
+use crate::timestamp::{self, TimeSpec};
+use chrono::{DateTime, FixedOffset, TimeDelta};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::cell::OnceCell;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Array values keyed by name; `""` is the array of a plain `\a`.
+pub(crate) type Arrays = HashMap<String, Vec<String>>;
+
+/// How deeply entries of pattern arrays may expand other pattern arrays.
+const MAX_PATTERN_DEPTH: usize = 16;
+
+/// How many extra repetitions `*`, `+` and `{n,}` may add when generating.
+pub(crate) const UNBOUNDED_REPEAT_SPAN: usize = 8;
 
 pub struct RegexGenerator {
     pattern: String,
+    macros: Macros, // Definitions added for \m{name}
+    root: Arc<Node>,
     groups: HashMap<usize, String>,
-    increment_value: Option<String>,
-    direction: i32, // 1 for ascending, -1 for descending
-    array_values: Option<Vec<String>>, // Optional array of strings
-    array_index: usize, // Index to track ascending or descending order
+    counters: HashMap<String, Counter>, // Keyed by name; "" is the plain \i counter
+    counter_mode: CounterMode,
+    advanced: HashSet<String>, // Counters already advanced in this call, in per-call mode
+    timestamps: HashMap<String, DateTime<FixedOffset>>, // Last value of each \T sequence
+    arrays: Arrays,
+    array_weights: HashMap<String, AliasTable>, // For random picks from weighted arrays
+    pattern_arrays: HashMap<String, Arc<Vec<Node>>>, // Parsed entries of arrays set as patterns
+    depth: usize, // Pattern array entries being expanded
+    table_columns: Arrays, // Columns of the tables, keyed as table.column
+    table_rows: HashMap<String, usize>, // Row each table uses in this call
+    array_cursors: HashMap<String, usize>, // Position of each \a+, \a- and \a~ cursor
+    decks: HashMap<String, (usize, Vec<usize>)>, // Pass and order of each \a~ cursor
+    deck_key: u64, // Shuffles the passes of \a~
+    clock: Clock, // Time of \u{uuid7}, \u{ulid} and \u{snowflake}
+    last_ulid: Option<u128>, // Keeps \u{ulid} increasing
+    last_snowflake: Option<u64>, // Keeps \u{snowflake} increasing
+    language: OnceCell<Result<Language, Error>>, // Compiled on first use
+    rng: SmallRng,
+    distribution: Distribution,
+    strategy: Strategy,
+    coverage: Option<CoverageWalk>, // Steers choices while building a coverage set
+    forced: HashMap<usize, usize>, // Branch or array index to use per node id
+    unique_key: u64, // Shuffles the order of generate_unique
+    unique_position: u128, // How many unique strings were handed out
 }
 
 impl RegexGenerator {
     pub fn new(pattern: &str, increment_value: Option<String>, array_values: Option<Vec<String>>) -> Self {
+        let mut rng = SmallRng::from_entropy();
+        let root = parser::parse(pattern, &Macros::new());
+        let mut arrays: Arrays = array_values.map(|values| (String::new(), values)).into_iter().collect();
+        dictionary::load(&root, &mut arrays);
         Self {
             pattern: pattern.to_string(),
+            macros: Macros::new(),
+            root: Arc::new(root),
             groups: HashMap::new(),
-            increment_value,
-            direction: 1, // default to ascending
-            array_values, // store the array of strings
-            array_index: 0, // start at the beginning of the array
+            counters: increment_value.map(|start| (String::new(), Counter::new(&start))).into_iter().collect(),
+            counter_mode: CounterMode::default(),
+            advanced: HashSet::new(),
+            timestamps: HashMap::new(),
+            arrays,
+            array_weights: HashMap::new(),
+            pattern_arrays: HashMap::new(),
+            depth: 0,
+            table_columns: HashMap::new(),
+            table_rows: HashMap::new(),
+            array_cursors: HashMap::new(),
+            decks: HashMap::new(),
+            deck_key: rng.gen(),
+            clock: Clock::default(),
+            last_ulid: None,
+            last_snowflake: None,
+            language: OnceCell::new(),
+            unique_key: rng.gen(),
+            rng,
+            distribution: Distribution::default(),
+            strategy: Strategy::default(),
+            coverage: None,
+            forced: HashMap::new(),
+            unique_position: 0,
         }
     }
 
-    pub fn generate(&mut self) -> String {
-        let mut result = String::new();
-        let mut chars = self.pattern.chars().peekable();
-        let mut group_stack: Vec<String> = Vec::new();
-        let mut current_group: Option<usize> = None;
-        let mut group_index: usize = 1;
-
-        while let Some(ch) = chars.next() {
-            if ch == '\\' {
-                if let Some(next_ch) = chars.next() {
-                    match next_ch {
-                        'i' => {
-                            // Check for + or - sign
-                            let sign = if chars.peek() == Some(&'+') {
-                                chars.next();
-                                1 // Ascending
-                            } else if chars.peek() == Some(&'-') {
-                                chars.next();
-                                -1 // Descending
-                            } else {
-                                1 // Default to ascending
-                            };
-
-                            self.direction = sign;
-
-                            // Check for leading zero specifier {:total_len}
-                            
-                            let total_len = if chars.peek() == Some(&'{') {
-                                chars.next(); // Skip the '{'
-                                let mut spec = String::new();
-                                while let Some(&c) = chars.peek() {
-                                    if c == '}' {
-                                        chars.next(); // Skip the '}'
-                                        break;
-                                    }
-                                    if c != ':' && c.is_numeric() {
-                                        spec.push(c);
-                                    }
-                                    chars.next();
-                                }
-                                spec.parse::<usize>().ok()
-                            } else {
-                                None
-                            };
-
-                            if let Some(increment_value) = self.increment_value.take() {
-                                let new_value = self.increment_string(&increment_value, total_len);
-                                result.push_str(&new_value);
-                                self.increment_value = Some(new_value);
-                            } else {
-                                result.push_str("0"); // Default to "0" or another placeholder
-                            }
-                        }
-                        'a' => {
-                            let array_sign = if chars.peek() == Some(&'+') {
-                                chars.next();
-                                1 // Ascending
-                            } else if chars.peek() == Some(&'-') {
-                                chars.next();
-                                -1 // Descending
-                            } else {
-                                0 // Random
-                            };
-
-                            if let Some(ref array) = self.array_values {
-                                match array_sign {
-                                    1 => {
-                                        // Ascending order
-                                        let value = &array[self.array_index % array.len()];
-                                        result.push_str(value);
-                                        self.array_index += 1;
-                                    }
-                                    -1 => {
-                                        // Descending order
-                                        let index = array.len() - 1 - (self.array_index % array.len());
-                                        let value = &array[index];
-                                        result.push_str(value);
-                                        self.array_index += 1;
-                                    }
-                                    _ => {
-                                        // Random order
-                                        let mut rng = rand::thread_rng();
-                                        let random_string = &array[rng.gen_range(0..array.len())];
-                                        result.push_str(random_string);
-                                    }
-                                }
-                            } else {
-                                result.push_str(""); // If no array is provided, insert nothing or handle as needed
-                            }
-                        }
-                        '1'..='9' => {
-                            if let Some(content) = self.groups.get(&(next_ch.to_digit(10).unwrap() as usize)) {
-                                result.push_str(content);
-                            }
-                        }
-                        _ => {
-                            if let Some(repeat_spec) = self.check_repeat_spec(&mut chars) {
-                                result.push_str(&self.handle_repeat(next_ch, repeat_spec));
-                            } else {
-                                result.push_str(&self.handle_escape(next_ch));
-                            }
-                        }
-                    }
-                }
-            } else if ch == '[' {
-                let (char_class, negate) = self.extract_char_class(&mut chars);
-                if let Some(repeat_spec) = self.check_repeat_spec(&mut chars) {
-                    result.push_str(&self.handle_bracket(char_class, repeat_spec, negate));
-                } else {
-                    result.push_str(&self.handle_bracket(char_class, (1, None, None), negate));
-                }
-            } else if ch == '(' {
-                if chars.peek() == Some(&'?') {
-                    chars.next(); // Skip the '?'
-                    // Handle non-capturing groups or other special groups here
-                }
-                current_group = Some(group_index);
-                group_stack.push(String::new());
-                group_index += 1;
-            } else if ch == ')' {
-                if let Some(group) = current_group {
-                    if let Some(mut content) = group_stack.pop() {
-                        if let Some(_alt_pos) = content.find('|') {
-                            let choices: Vec<&str> = content.split('|').collect();
-                            content = choices[0].to_string();
-                        }
-                        self.groups.insert(group, content.clone());
-                        result.push_str(&content);
-                        current_group = None;
-                    }
-                }
-            } else if ch == '|' {
-                if let Some(last) = group_stack.last_mut() {
-                    last.push('|');
-                } else {
-                    result.push('|');
-                }
-            } else {
-                if let Some(ref mut _current) = current_group {
-                    if let Some(last) = group_stack.last_mut() {
-                        last.push(ch);
-                    }
-                } else {
-                    result.push(ch);
-                }
-            }
+    /// Defines the macro `\m{name}`, which stands for `(?:pattern)`. It
+    /// replaces a standard library macro of the same name, but not one the
+    /// pattern defines itself with `(?(DEFINE)(?<name>...))`.
+    pub fn set_macro(&mut self, name: &str, pattern: &str) {
+        self.macros.insert(name.to_string(), pattern.to_string());
+        self.reparse();
+    }
+
+    /// Defines the macros of a library file of `name = pattern` lines, as
+    /// read by [`parse_library`](crate::parse_library).
+    pub fn load_library(&mut self, text: &str) -> Result<(), Error> {
+        self.macros.extend(library::parse_library(text)?);
+        self.reparse();
+        Ok(())
+    }
+
+    /// Parses the pattern and the entries of pattern arrays again after
+    /// the macros changed; the entries are parsed from their array values.
+    fn reparse(&mut self) {
+        let root = parser::parse(&self.pattern, &self.macros);
+        dictionary::load(&root, &mut self.arrays);
+        self.root = Arc::new(root);
+        self.language = OnceCell::new();
+        let names: Vec<String> = self.pattern_arrays.keys().cloned().collect();
+        for name in names {
+            self.parse_pattern_array(&name);
         }
+    }
 
-        result
+    /// Adds or replaces the counter used by `\i{name}`; an empty name sets
+    /// the counter of a plain `\i`.
+    pub fn set_counter(&mut self, name: &str, counter: Counter) {
+        self.counters.insert(name.to_string(), counter);
     }
 
-    fn check_repeat_spec<I>(&self, chars: &mut std::iter::Peekable<I>) -> Option<(usize, Option<usize>, Option<(usize, usize)>)>
-    where
-        I: Iterator<Item = char>,
-    {
-        if chars.peek() == Some(&'{') {
-            chars.next(); // Skip the '{'
-            let mut spec = String::new();
+    /// The counter used by `\i{name}`, if one was set.
+    pub fn counter(&self, name: &str) -> Option<&Counter> {
+        self.counters.get(name)
+    }
 
-            while let Some(&c) = chars.peek() {
-                if c == '}' {
-                    chars.next(); // Skip the '}'
-                    break;
-                }
-                spec.push(c);
-                chars.next();
-            }
+    /// Adds or replaces the values of `\a{name}`; an empty name sets the
+    /// array of a plain `\a`. The values are equally likely until
+    /// [`set_array_weights`](Self::set_array_weights) weighs them.
+    pub fn set_array(&mut self, name: &str, values: Vec<String>) {
+        self.arrays.insert(name.to_string(), values);
+        self.array_weights.remove(name);
+        self.pattern_arrays.remove(name);
+        self.decks.clear();
+        self.language = OnceCell::new();
+    }
+
+    /// Like [`set_array`](Self::set_array), but each value is a pattern that
+    /// is generated when `\a{name}` picks it, as in `ORD-\d{6}`. Entries
+    /// share the counters of the main pattern and can refer back to its
+    /// groups; their own groups are numbered from 1 and end with the entry.
+    /// An entry may use other pattern arrays, up to a fixed depth, after
+    /// which generating returns [`Error::RecursionLimit`].
+    pub fn set_pattern_array(&mut self, name: &str, patterns: Vec<String>) {
+        self.set_array(name, patterns);
+        self.parse_pattern_array(name);
+    }
+
+    /// Parses the values of the array `name` as its pattern entries and
+    /// loads the dictionaries they use.
+    fn parse_pattern_array(&mut self, name: &str) {
+        let entries: Vec<Node> = self.arrays[name]
+            .iter()
+            .enumerate()
+            .map(|(i, pattern)| parser::parse_entry(pattern, &self.macros, &format!("{}[{}]", name, i)))
+            .collect();
+        for entry in &entries {
+            dictionary::load(entry, &mut self.arrays);
+        }
+        self.pattern_arrays.insert(name.to_string(), Arc::new(entries));
+    }
+
+    /// Weighs the random picks of `\a{name}`: each value is drawn with
+    /// probability proportional to its weight. Returns
+    /// [`Error::InvalidArray`] unless the array is set, there is one weight
+    /// per value, and the weights are non-negative and not all zero.
+    pub fn set_array_weights(&mut self, name: &str, weights: Vec<f64>) -> Result<(), Error> {
+        let Some(values) = self.arrays.get(name) else {
+            return Err(Error::InvalidArray(format!("no array named '{}' to weigh", name)));
+        };
+        if weights.len() != values.len() {
+            return Err(Error::InvalidArray(format!("{} weights for {} values", weights.len(), values.len())));
+        }
+        let table = AliasTable::new(weights)
+            .ok_or_else(|| Error::InvalidArray("weights must be non-negative and not all zero".to_string()))?;
+        self.array_weights.insert(name.to_string(), table);
+        Ok(())
+    }
 
-            if let Some(colon_pos) = spec.find(':') {
-                // Handle leading zeros pattern {num_len:total_len}
-                let num_len = spec[..colon_pos].parse().ok()?;
-                let total_len = spec[colon_pos + 1..].parse().ok()?;
-                return Some((1, None, Some((num_len, total_len))));
-            } else {
-                // Handle regular repeat pattern {min,max}
-                let parts: Vec<&str> = spec.split(',').collect();
-                if parts.len() == 1 {
-                    return Some((parts[0].parse().unwrap(), None, None));
-                } else if parts.len() == 2 {
-                    return Some((parts[0].parse().unwrap(), Some(parts[1].parse().unwrap()), None));
+    /// The values of `\a{name}`, if they were set.
+    pub fn array(&self, name: &str) -> Option<&[String]> {
+        self.arrays.get(name).map(Vec::as_slice)
+    }
+
+    /// Adds or replaces the table `name`, whose columns `\a{name.column}`
+    /// reads. Every column of a table takes its value from the same row in
+    /// one generated string: the row the first of them picks, by its order.
+    /// Returns [`Error::InvalidArray`] when a row does not have one value
+    /// per column.
+    pub fn set_table(&mut self, name: &str, columns: Vec<String>, rows: Vec<Vec<String>>) -> Result<(), Error> {
+        if let Some(row) = rows.iter().find(|row| row.len() != columns.len()) {
+            return Err(Error::InvalidArray(format!("row {:?} does not have {} columns", row, columns.len())));
+        }
+        let prefix = format!("{}.", name);
+        self.table_columns.retain(|key, _| !key.starts_with(&prefix));
+        for (i, column) in columns.iter().enumerate() {
+            let values = rows.iter().map(|row| row[i].clone()).collect();
+            self.table_columns.insert(format!("{}{}", prefix, column.trim()), values);
+        }
+        self.decks.clear();
+        Ok(())
+    }
+
+    /// Where the counters, ordered arrays and unique sequence stand, for
+    /// [`restore_state`](Self::restore_state) to pick up later.
+    pub fn state(&self) -> GeneratorState {
+        GeneratorState {
+            counters: self.counters.iter().map(|(name, counter)| (name.clone(), counter.value.clone())).collect(),
+            array_cursors: self.array_cursors.iter().map(|(name, cursor)| (name.clone(), *cursor)).collect(),
+            unique_key: self.unique_key,
+            deck_key: self.deck_key,
+            unique_position: self.unique_position,
+            timestamps: self.timestamps.iter().map(|(source, time)| (source.clone(), time.to_rfc3339())).collect(),
+            last_ulid: self.last_ulid,
+            last_snowflake: self.last_snowflake,
+        }
+    }
+
+    /// Continues from a saved state. Counters already set keep their
+    /// settings and take the saved value; the others start from it with
+    /// default settings.
+    pub fn restore_state(&mut self, state: GeneratorState) {
+        for (name, value) in state.counters {
+            match self.counters.get_mut(&name) {
+                Some(counter) => counter.value = value,
+                None => {
+                    self.counters.insert(name, Counter::new(&value));
                 }
             }
         }
-
-        None
-    }
-
-    fn handle_escape(&self, ch: char) -> String {
-        let mut rng = rand::thread_rng();
-
-        match ch {
-            'd' => rng.gen_range(0..10).to_string(), // \d - any digit
-            'w' => {
-                let sample_set = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789_";
-                sample_set.chars().nth(rng.gen_range(0..sample_set.len())).unwrap().to_string()
-            } // \w - any word character
-            's' => {
-                let sample_set = " \t\n\r";
-                sample_set.chars().nth(rng.gen_range(0..sample_set.len())).unwrap().to_string()
-            } // \s - any whitespace
-            'D' => {
-                let sample_set = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz!@#$%^&*()";
-                sample_set.chars().nth(rng.gen_range(0..sample_set.len())).unwrap().to_string()
-            } // \D - any non-digit character
-            'W' => {
-                let sample_set = "!@#$%^&*()+=-[]{}|;:,.<>?/`~";
-                sample_set.chars().nth(rng.gen_range(0..sample_set.len())).unwrap().to_string()
-            } // \W - any non-word character
-            'S' => {
-                let sample_set = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789!@#$%^&*()";
-                sample_set.chars().nth(rng.gen_range(0..sample_set.len())).unwrap().to_string()
-            } // \S - any non-whitespace character
-            't' => "\t".to_string(), // \t - Tab character
-            'n' => "\n".to_string(), // \n - Line feed character
-            _ => ch.to_string(),
+        self.array_cursors = state.array_cursors.into_iter().collect();
+        self.decks.clear();
+        self.deck_key = state.deck_key;
+        self.unique_key = state.unique_key;
+        self.unique_position = state.unique_position;
+        self.last_ulid = state.last_ulid;
+        self.last_snowflake = state.last_snowflake;
+        for (source, time) in state.timestamps {
+            if let Some(time) = timestamp::parse_time(&time) {
+                self.timestamps.insert(source, time);
+            }
         }
     }
 
-    fn handle_repeat(&self, ch: char, repeat_spec: (usize, Option<usize>, Option<(usize, usize)>)) -> String {
-        let (min, max, leading_zeros_spec) = repeat_spec;
-        let mut rng = rand::thread_rng();
-        let repeat_count = if let Some(max) = max {
-            rng.gen_range(min..=max)
-        } else {
-            min
-        };
+    /// Chooses whether counters advance at every `\i` or once per call.
+    pub fn set_counter_mode(&mut self, mode: CounterMode) {
+        self.counter_mode = mode;
+    }
 
-        if let Some((num_len, total_len)) = leading_zeros_spec {
-            // Handle leading zeros pattern
-            let number = rng.gen_range(10_usize.pow((num_len - 1) as u32)..10_usize.pow(num_len as u32));
-            return format!("{:0width$}", number, width = total_len);
-        } else {
-            // Handle regular repeat pattern
-            return std::iter::repeat(self.handle_escape(ch))
-                .take(repeat_count)
-                .collect();
+    /// Sets the time `\u{uuid7}`, `\u{ulid}` and `\u{snowflake}` are made
+    /// at; with a fixed clock and a seed their identifiers repeat.
+    pub fn set_clock(&mut self, clock: Clock) {
+        self.clock = clock;
+    }
+
+    /// Makes the random choices reproducible: two generators with the same
+    /// pattern, inputs and seed produce the same strings.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = SmallRng::seed_from_u64(seed);
+        self.unique_key = self.rng.gen();
+        self.deck_key = self.rng.gen();
+        self.decks.clear();
+    }
+
+    /// Generates one string.
+    ///
+    /// # Panics
+    ///
+    /// Panics when a counter set to stop at its bound runs past it, when
+    /// pattern arrays expand each other too deeply, or when a ULID or
+    /// Snowflake ID has no value left above the last one; use
+    /// [`try_generate`](Self::try_generate) to handle these cases.
+    pub fn generate(&mut self) -> String {
+        self.try_generate().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Generates one string, or returns [`Error::CounterOutOfRange`] when a
+    /// counter set to stop at its bound would run past it,
+    /// [`Error::RecursionLimit`] when pattern arrays nest too deeply and
+    /// [`Error::IdentifierOverflow`] when a ULID or Snowflake ID cannot
+    /// exceed the last one.
+    pub fn try_generate(&mut self) -> Result<String, Error> {
+        let mut result = String::new();
+        let root = Arc::clone(&self.root);
+        self.groups.clear();
+        self.table_rows.clear();
+        self.depth = 0;
+        self.advanced.clear();
+        if let Some(value) = self.uniform_draw() {
+            return Ok(value);
         }
+        self.generate_node(&root, &mut result)?;
+        Ok(result)
     }
 
-    fn extract_char_class<I>(&self, chars: &mut std::iter::Peekable<I>) -> (HashSet<char>, bool)
-    where
-        I: Iterator<Item = char>,
-    {
-        let mut char_class = HashSet::new();
-        let mut negate = false;
-        let mut range_start = None;
+    /// Under [`Distribution::UniformLanguage`], draws the string at a random
+    /// index of the language, so a string the pattern builds in several ways
+    /// is no more likely than the others. Returns `None` when the pattern
+    /// cannot be counted exactly, or uses cursors, weights or a strategy
+    /// that an index would ignore; weights per node stand in then.
+    fn uniform_draw(&mut self) -> Option<String> {
+        let exact = self.distribution == Distribution::UniformLanguage
+            && matches!(self.strategy, Strategy::Random)
+            && self.coverage.is_none()
+            && self.forced.is_empty()
+            && self.is_counted_exactly(&self.root);
+        if !exact {
+            return None;
+        }
+        let count = self.language().ok()?.cardinality().filter(|&count| count > 0)?;
+        let index = self.rng.gen_range(0..count);
+        self.language().ok()?.nth(index)
+    }
 
-        if chars.peek() == Some(&'^') {
-            chars.next();
-            negate = true;
+    /// Whether every choice in `node` counts once per string it leads to:
+    /// no weighted branches or arrays, and no arrays read through a cursor.
+    fn is_counted_exactly(&self, node: &Node) -> bool {
+        match node {
+            Node::Group { weights: Some(_), .. } => false,
+            Node::Group { alternatives, .. } => alternatives.iter().flatten().all(|node| self.is_counted_exactly(node)),
+            Node::Repeat { node, .. } => self.is_counted_exactly(node),
+            Node::Array { name, order, .. } => *order == ArrayOrder::Random && !self.array_weights.contains_key(name),
+            _ => true,
         }
+    }
+
+    /// Lists every distinct string the pattern can produce, in `order`.
+    ///
+    /// `max_len` caps the length of the listed strings; it is required for
+    /// [`Order::Lexicographic`] on patterns with `*`, `+` or `{n,}`. The array
+    /// values count as alternatives, while `\i`, `\I` and backreferences make
+    /// the pattern unsupported.
+    pub fn enumerate(&self, order: Order, max_len: Option<usize>) -> Result<Enumerate<'_>, Error> {
+        self.language()?.iter(order, max_len)
+    }
+
+    /// Number of distinct strings the pattern can produce.
+    ///
+    /// Returns `Ok(None)` when the pattern is unbounded or the count does not
+    /// fit in a `u128`, and an error for the patterns `enumerate` rejects.
+    pub fn cardinality(&self) -> Result<Option<u128>, Error> {
+        Ok(self.language()?.cardinality())
+    }
+
+    /// The string at `index` in [`Order::Shortlex`], so `nth(0)` is the
+    /// first string `enumerate` lists. Together with [`index_of`](Self::index_of)
+    /// this is a bijection between `0..cardinality()` and the pattern's
+    /// strings, which makes it safe to split generation by index range.
+    ///
+    /// Returns `None` when `index` is out of range, the pattern cannot be
+    /// enumerated, or reaching `index` means stepping over more than
+    /// `u128::MAX` strings.
+    pub fn nth(&self, index: u128) -> Option<String> {
+        self.language().ok()?.nth(index)
+    }
 
-        while let Some(ch) = chars.next() {
-            if ch == ']' {
+    /// The index `nth` maps to `value`, or `None` if the pattern cannot
+    /// produce `value`.
+    pub fn index_of(&self, value: &str) -> Option<u128> {
+        self.language().ok()?.index_of(value)
+    }
+
+    /// Chooses how `generate` weighs alternation branches and repeat counts.
+    pub fn set_distribution(&mut self, distribution: Distribution) {
+        self.distribution = distribution;
+    }
+
+    /// Chooses between random values and deliberately extreme ones.
+    pub fn set_strategy(&mut self, strategy: Strategy) {
+        self.strategy = strategy;
+    }
+
+    /// Builds a small set of strings that together reach every alternation
+    /// branch, the minimum, maximum and one middle count of every repeat
+    /// (so both the absent and present state of `?`), and the first and
+    /// last character of every range in a class.
+    ///
+    /// Each string is chosen greedily to reach as many goals not yet
+    /// covered as possible, and lists every goal it reaches. Fails like
+    /// [`try_generate`](Self::try_generate).
+    pub fn coverage_set(&mut self) -> Result<Vec<Coverage>, Error> {
+        self.coverage = Some(CoverageWalk::new(&self.root));
+        let mut set = Vec::new();
+        loop {
+            let value = match self.try_generate() {
+                Ok(value) => value,
+                Err(err) => {
+                    self.coverage = None;
+                    return Err(err);
+                }
+            };
+            let walk = self.coverage.as_mut().unwrap();
+            let (reached_new, covers) = walk.finish_string();
+            // Always return at least one string, even for a pattern without goals.
+            if reached_new || set.is_empty() {
+                set.push(Coverage { value, covers });
+            }
+            if !reached_new || walk.is_complete() {
                 break;
-            } else if ch == '-' && range_start.is_some() {
-                if let Some(range_end) = chars.next() {
-                    let start = range_start.unwrap();
-                    for c in start..=range_end {
-                        char_class.insert(c);
+            }
+        }
+        self.coverage = None;
+        Ok(set)
+    }
+
+    /// Generates a compact set of strings in which every pair of choices
+    /// from two different choice points appears at least once.
+    ///
+    /// The choice points are alternations with more than one branch and
+    /// random `\a` arrays; for `(GET|POST) \a (v1|v2|v3)` every method meets
+    /// every array value and every version, without listing all
+    /// combinations. Everything else, including choice points inside
+    /// repeats, stays random. Fails like [`try_generate`](Self::try_generate).
+    pub fn pairwise_set(&mut self) -> Result<Vec<String>, Error> {
+        let cases = pairwise::plan(&self.root, &self.arrays);
+        let set = cases
+            .into_iter()
+            .map(|case| {
+                self.forced = case;
+                self.try_generate()
+            })
+            .collect();
+        self.forced.clear();
+        set
+    }
+
+    /// Generates a string that no earlier call has returned, until every
+    /// string of the pattern has been handed out; after that it returns
+    /// [`Error::Exhausted`].
+    ///
+    /// The strings come in a shuffled order from a keyed permutation of the
+    /// indexes used by [`nth`](Self::nth), so memory use does not grow with
+    /// the number of calls. The pattern must be one `enumerate` accepts and
+    /// must be finite.
+    pub fn generate_unique(&mut self) -> Result<String, Error> {
+        let language = self.language()?;
+        let domain = match language.cardinality() {
+            Some(count) => count,
+            None if language.is_infinite() => return Err(Error::InfiniteLanguage),
+            None => u128::MAX,
+        };
+        if self.unique_position >= domain {
+            return Err(Error::Exhausted);
+        }
+        let index = Permutation::new(domain, self.unique_key).apply(self.unique_position);
+        let value = language.nth(index).ok_or(Error::Exhausted)?;
+        self.unique_position += 1;
+        Ok(value)
+    }
+
+    fn language(&self) -> Result<&Language, Error> {
+        self.language
+            .get_or_init(|| {
+                let mut pattern_array = None;
+                self.root.for_each_array(&mut |name| {
+                    if self.pattern_arrays.contains_key(name) {
+                        pattern_array.get_or_insert_with(|| name.to_string());
+                    }
+                });
+                match pattern_array {
+                    Some(name) => Err(Error::UnsupportedToken(format!("\\a{{{}}}", name))),
+                    None => Language::compile(&self.root, &self.arrays),
+                }
+            })
+            .as_ref()
+            .map_err(Clone::clone)
+    }
+
+    fn generate_node(&mut self, node: &Node, result: &mut String) -> Result<(), Error> {
+        match node {
+            Node::Literal(ch) => result.push(*ch),
+            Node::Class(sample_set) => {
+                if !sample_set.is_empty() {
+                    let ch = self.choose_char(node, sample_set);
+                    result.push(ch);
+                }
+            }
+            Node::Group { index, alternatives, weights } => {
+                let alternative = &alternatives[self.choose_branch(node, alternatives, weights.as_deref())];
+                let mut content = String::new();
+                for node in alternative {
+                    self.generate_node(node, &mut content)?;
+                }
+                if let Some(index) = index {
+                    self.groups.insert(*index, content.clone());
+                }
+                result.push_str(&content);
+            }
+            Node::Repeat { node: inner, min, max } => {
+                for _ in 0..self.choose_count(node, inner, *min, *max) {
+                    self.generate_node(inner, result)?;
+                }
+            }
+            Node::PaddedNumber { digits, width } => {
+                // Handle leading zeros pattern
+                if self.at_boundary() {
+                    let (first, rest) = if self.rng.gen_bool(0.5) { ('1', '0') } else { ('9', '9') };
+                    let number: String = std::iter::once(first).chain(std::iter::repeat_n(rest, digits - 1)).collect();
+                    result.push_str(&format!("{:0>width$}", number, width = width));
+                    return Ok(());
+                }
+                let number: String = std::iter::once(self.rng.gen_range(1..10))
+                    .chain((1..*digits).map(|_| self.rng.gen_range(0..10)))
+                    .map(|digit: u32| char::from_digit(digit, 10).unwrap())
+                    .collect();
+                result.push_str(&format!("{:0>width$}", number, width = width));
+            }
+            Node::Backreference(index) => {
+                if let Some(content) = self.groups.get(index) {
+                    result.push_str(content);
+                }
+            }
+            Node::Increment { name, options } => result.push_str(&self.counter_value(name, Some(options))?),
+            Node::Current { name } => result.push_str(&self.counter_value(name, None)?),
+            Node::Number(spec) => {
+                let value = if self.at_boundary() {
+                    if self.rng.gen_bool(0.5) { spec.min } else { spec.max }
+                } else {
+                    spec.sample(&mut self.rng)
+                };
+                result.push_str(&spec.render(value));
+            }
+            Node::Identifier(spec) => result.push_str(&self.identifier(spec)?),
+            Node::Timestamp(spec) => {
+                let time = match spec.end {
+                    Some(end) if self.at_boundary() => if self.rng.gen_bool(0.5) { spec.start } else { end },
+                    Some(_) => spec.start + TimeDelta::seconds(self.rng.gen_range(0..=spec.span_seconds()) as i64),
+                    None => self.next_timestamp(spec),
+                };
+                result.push_str(&spec.render(time));
+            }
+            Node::Array { name, order, cursor } => {
+                let boundary = *order == ArrayOrder::Random && self.at_boundary();
+                // If no array is provided, insert nothing
+                let Some(array) = self.arrays.get(name).or_else(|| self.table_columns.get(name)) else {
+                    return Ok(());
+                };
+                if array.is_empty() {
+                    return Ok(());
+                }
+                // Columns of a table all take the row its first column picked.
+                let table = name.split_once('.').map(|(table, _)| table).filter(|_| self.table_columns.contains_key(name));
+                if let Some(&row) = table.and_then(|table| self.table_rows.get(table)) {
+                    result.push_str(&array[row]);
+                    return Ok(());
+                }
+                let index = match order {
+                    ArrayOrder::Ascending => {
+                        let cursor = self.array_cursors.entry(cursor.clone()).or_insert(0);
+                        *cursor += 1;
+                        (*cursor - 1) % array.len()
+                    }
+                    ArrayOrder::Descending => {
+                        let cursor = self.array_cursors.entry(cursor.clone()).or_insert(0);
+                        *cursor += 1;
+                        array.len() - 1 - ((*cursor - 1) % array.len())
+                    }
+                    ArrayOrder::Deck => {
+                        let position = self.array_cursors.entry(cursor.clone()).or_insert(0);
+                        let (pass, offset) = (*position / array.len(), *position % array.len());
+                        *position += 1;
+                        let deck = self.decks.entry(cursor.clone()).or_default();
+                        if deck.0 != pass || deck.1.len() != array.len() {
+                            *deck = (pass, permutation::deck(array.len(), self.deck_key, cursor, pass));
+                        }
+                        deck.1[offset]
+                    }
+                    ArrayOrder::Random if self.forced.contains_key(&node.id()) => self.forced[&node.id()],
+                    ArrayOrder::Random if boundary => {
+                        let length = |i: &usize| array[*i].chars().count();
+                        let shortest = (0..array.len()).min_by_key(length).unwrap();
+                        let longest = (0..array.len()).max_by_key(length).unwrap();
+                        if self.rng.gen_bool(0.5) { shortest } else { longest }
                     }
-                    range_start = None;
+                    ArrayOrder::Random => match self.array_weights.get(name) {
+                        Some(table) => table.sample(&mut self.rng),
+                        None => self.rng.gen_range(0..array.len()),
+                    },
+                };
+                if let Some(table) = table {
+                    self.table_rows.insert(table.to_string(), index);
+                }
+                if let Some(entries) = self.pattern_arrays.get(name).map(Arc::clone) {
+                    return self.generate_entry(name, &entries[index], result);
                 }
-            } else {
-                range_start = Some(ch);
-                char_class.insert(ch);
+                result.push_str(&array[index]);
             }
         }
+        Ok(())
+    }
 
-        (char_class, negate)
+    /// Generates a pattern array entry, keeping its groups to itself.
+    fn generate_entry(&mut self, name: &str, entry: &Node, result: &mut String) -> Result<(), Error> {
+        if self.depth == MAX_PATTERN_DEPTH {
+            return Err(Error::RecursionLimit(name.to_string()));
+        }
+        let groups = self.groups.clone();
+        self.depth += 1;
+        let generated = self.generate_node(entry, result);
+        self.depth -= 1;
+        self.groups = groups;
+        generated
     }
 
-    fn handle_bracket(&self, char_class: HashSet<char>, repeat_spec: (usize, Option<usize>, Option<(usize, usize)>), negate: bool) -> String {
-        let (min, max, leading_zeros_spec) = repeat_spec;
-        let mut rng = rand::thread_rng();
-        let repeat_count = if let Some(max) = max {
-            rng.gen_range(min..=max)
-        } else {
-            min
+    /// The value a `\i` (with its `options`) or `\I` (without) inserts.
+    fn counter_value(&mut self, name: &str, options: Option<&CounterOptions>) -> Result<String, Error> {
+        let Some(counter) = self.counters.get_mut(name) else {
+            return Ok("0".to_string()); // Default to "0" or another placeholder
+        };
+        let advance = match self.counter_mode {
+            CounterMode::PerOccurrence => options.is_some(),
+            CounterMode::PerCall => self.advanced.insert(name.to_string()),
         };
+        if !advance {
+            return Ok(counter.value.clone());
+        }
+        // A \I that comes first in per-call mode advances the counter with
+        // the settings of the counter's \i in the pattern.
+        let root = Arc::clone(&self.root);
+        let options = options.or_else(|| root.counter_options(name));
+        counter.advance(name, options.unwrap_or(&CounterOptions::default()))
+    }
 
-        if let Some((num_len, total_len)) = leading_zeros_spec {
-            // Handle leading zeros pattern
-            let number = rng.gen_range(10_usize.pow((num_len - 1) as u32)..10_usize.pow(num_len as u32));
-            return format!("{:0width$}", number, width = total_len);
-        } else {
-            let sample_set: Vec<char> = if negate {
-                let full_set: HashSet<char> = (32..127).map(|c| c as u8 as char).collect();
-                full_set.difference(&char_class).cloned().collect()
-            } else {
-                char_class.into_iter().collect()
-            };
+    /// A new `\u{...}` identifier, with its random bits from the generator's rng.
+    fn identifier(&mut self, spec: &IdSpec) -> Result<String, Error> {
+        Ok(match *spec {
+            IdSpec::Uuid4 => identifier::uuid4(self.rng.gen()),
+            IdSpec::Uuid7 => identifier::uuid7(self.clock.now(), self.rng.gen()),
+            IdSpec::Ulid => {
+                let ulid = identifier::next_ulid(self.last_ulid, self.clock.now(), self.rng.gen())
+                    .ok_or_else(|| Error::IdentifierOverflow("ulid".to_string()))?;
+                self.last_ulid = Some(ulid);
+                identifier::ulid(ulid)
+            }
+            IdSpec::NanoId(length) => identifier::nanoid((0..length).map(|_| self.rng.gen())),
+            IdSpec::Snowflake(worker) => {
+                let snowflake = identifier::next_snowflake(self.last_snowflake, self.clock.now(), worker)
+                    .ok_or_else(|| Error::IdentifierOverflow("snowflake".to_string()))?;
+                self.last_snowflake = Some(snowflake);
+                snowflake.to_string()
+            }
+        })
+    }
 
-            return (0..repeat_count)
-                .map(|_| sample_set[rng.gen_range(0..sample_set.len())])
-                .collect();
+    /// The next timestamp of a sequence: its start on first use, then one
+    /// step further each time. Per-call mode advances it like a counter.
+    fn next_timestamp(&mut self, spec: &TimeSpec) -> DateTime<FixedOffset> {
+        let Some(&last) = self.timestamps.get(&spec.source) else {
+            self.advanced.insert(format!("\\T{{{}}}", spec.source));
+            self.timestamps.insert(spec.source.clone(), spec.start);
+            return spec.start;
+        };
+        let advance = match self.counter_mode {
+            CounterMode::PerOccurrence => true,
+            CounterMode::PerCall => self.advanced.insert(format!("\\T{{{}}}", spec.source)),
+        };
+        if !advance {
+            return last;
         }
+        let next = last.checked_add_signed(spec.step).unwrap_or(last);
+        self.timestamps.insert(spec.source.clone(), next);
+        next
     }
 
-    fn increment_string(&self, value: &str, total_len: Option<usize>) -> String {
-        let mut prefix = String::new();
-        let mut digits = String::new();
-        // Separate prefix and numeric part
-        for ch in value.chars() {
-            if ch.is_digit(10) {
-                digits.push(ch);
-            } else {
-                if digits.is_empty() {
-                    prefix.push(ch);
-                } else {
-                    break;
+    fn choose_char(&mut self, node: &Node, sample_set: &[char]) -> char {
+        let ch = if let Some(ch) = self.coverage.as_ref().and_then(|walk| walk.choose_char(node, sample_set)) {
+            ch
+        } else if self.at_boundary() {
+            let endpoints = strategy::class_endpoints(sample_set);
+            endpoints[self.rng.gen_range(0..endpoints.len())]
+        } else {
+            sample_set[self.rng.gen_range(0..sample_set.len())]
+        };
+        if let Some(walk) = &mut self.coverage {
+            walk.hit(node, Goal::Char(ch));
+        }
+        ch
+    }
+
+    fn choose_branch(&mut self, node: &Node, alternatives: &[Vec<Node>], weights: Option<&AliasTable>) -> usize {
+        let empty: Vec<usize> = (0..alternatives.len()).filter(|&i| alternatives[i].is_empty()).collect();
+        let choice = if let Some(&choice) = self.forced.get(&node.id()) {
+            choice
+        } else if let Some(choice) = self.coverage.as_ref().and_then(|walk| walk.choose_branch(node, alternatives)) {
+            choice
+        } else if !empty.is_empty() && self.at_boundary() {
+            empty[self.rng.gen_range(0..empty.len())]
+        } else {
+            match (self.distribution, weights) {
+                (Distribution::PerNode, Some(table)) => table.sample(&mut self.rng),
+                (Distribution::PerNode, None) => self.rng.gen_range(0..alternatives.len()),
+                // Branch weights scale the number of strings each branch stands for.
+                (Distribution::UniformLanguage, _) => {
+                    let log_weights: Vec<f64> = alternatives
+                        .iter()
+                        .enumerate()
+                        .map(|(i, alternative)| {
+                            let branch_weight = weights.map_or(0.0, |table| table.weights[i].ln());
+                            distribution::sequence_log_weight(alternative, &self.arrays) + branch_weight
+                        })
+                        .collect();
+                    distribution::pick(&log_weights, &mut self.rng)
                 }
             }
+        };
+        if let Some(walk) = &mut self.coverage {
+            walk.hit(node, Goal::Branch(choice));
         }
+        choice
+    }
 
-        // Adjust numeric part based on the direction (ascending or descending)
-        if let Ok(num) = digits.parse::<i32>() {
-            let adjusted_num = num + self.direction;
-            digits = if let Some(total_len) = total_len {
-                format!("{:0width$}", adjusted_num, width = total_len)
-            } else {
-                format!("{}", adjusted_num)
-            };
+    fn choose_count(&mut self, node: &Node, inner: &Node, min: usize, max: Option<usize>) -> usize {
+        let guided = self.coverage.as_ref().and_then(|walk| walk.choose_count(node, inner, min, max));
+        let max = max.unwrap_or(min + UNBOUNDED_REPEAT_SPAN);
+        let count = if let Some(count) = guided {
+            count
+        } else if self.at_boundary() {
+            if self.rng.gen_bool(0.5) { min } else { max }
+        } else {
+            match self.distribution {
+                Distribution::PerNode => self.rng.gen_range(min..=max),
+                Distribution::UniformLanguage => {
+                    let node_weight = distribution::log_weight(inner, &self.arrays);
+                    let weights: Vec<f64> = (min..=max).map(|count| count as f64 * node_weight).collect();
+                    min + distribution::pick(&weights, &mut self.rng)
+                }
+            }
+        };
+        if let Some(walk) = &mut self.coverage {
+            walk.hit(node, Goal::Count(count));
         }
+        count
+    }
 
-        // Combine prefix and adjusted numeric part
-        format!("{}{}", prefix, digits)
+    /// Whether the next choice should go to a boundary value.
+    fn at_boundary(&mut self) -> bool {
+        match self.strategy {
+            Strategy::Random => false,
+            Strategy::Boundary => true,
+            // NaN fails the comparison, so it behaves like 0.
+            Strategy::Mixed(probability) => probability > 0.0 && self.rng.gen_bool(probability.min(1.0)),
+        }
     }
 }
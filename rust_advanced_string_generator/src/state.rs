@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The progress of a [`RegexGenerator`](crate::RegexGenerator): where its
+/// counters, ordered arrays and unique sequence stand. Saving it and
+/// restoring it into a new generator for the same pattern continues where
+/// the first one stopped.
+///
+/// The random number generator is not part of the state, so a restored
+/// generator with a fixed seed repeats its earlier random choices.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GeneratorState {
+    /// Current value of each counter, keyed by name; `""` is the counter of
+    /// a plain `\i`.
+    pub counters: BTreeMap<String, String>,
+    /// Position of `\a+`, `\a-` and `\a~` in each array, keyed by name for the
+    /// first occurrence in the pattern (`""` for the unnamed array),
+    /// `name#2` and up for later ones and `name@cursor` for shared cursors.
+    /// Occurrences inside entry `i` of the pattern array `list` are keyed
+    /// `list[i]:name`, `list[i]:name#2` and so on.
+    pub array_cursors: BTreeMap<String, usize>,
+    /// Key of the shuffled order `generate_unique` follows.
+    pub unique_key: u64,
+    /// Key of the shuffles `\a~` deals its passes in.
+    pub deck_key: u64,
+    /// How many strings `generate_unique` has handed out.
+    pub unique_position: u128,
+    /// Last timestamp of each `\T` sequence in RFC 3339, keyed by the text
+    /// between its braces.
+    pub timestamps: BTreeMap<String, String>,
+    /// Last `\u{ulid}` as a number, which the next one must exceed; `None`
+    /// when none was made.
+    pub last_ulid: Option<u128>,
+    /// Last `\u{snowflake}`, which the next one must exceed; `None` when none
+    /// was made.
+    pub last_snowflake: Option<u64>,
+}
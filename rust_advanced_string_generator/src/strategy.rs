@@ -0,0 +1,31 @@
+/// Whether `generate` aims for typical or extreme values.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Strategy {
+    /// Every choice is random (the default).
+    #[default]
+    Random,
+    /// Every choice is a boundary: the minimum or maximum of a repeat, the
+    /// first or last character of each range in a class, an empty
+    /// alternation branch, the smallest or largest padded number or `\r`
+    /// range value, the earliest or latest random timestamp, and the
+    /// shortest or longest array value.
+    Boundary,
+    /// Each choice is a boundary with the given probability (0.0 to 1.0)
+    /// and random otherwise. Probabilities outside that range are clamped to
+    /// it, and NaN counts as 0.0.
+    Mixed(f64),
+}
+
+/// The first and last character of each run of consecutive characters in a
+/// sorted class, so `[a-zA-Z]` gives `A`, `Z`, `a` and `z`.
+pub(crate) fn class_endpoints(set: &[char]) -> Vec<char> {
+    let mut endpoints = Vec::new();
+    for (i, &ch) in set.iter().enumerate() {
+        let starts_run = i == 0 || set[i - 1] as u32 + 1 != ch as u32;
+        let ends_run = i + 1 == set.len() || ch as u32 + 1 != set[i + 1] as u32;
+        if starts_run || ends_run {
+            endpoints.push(ch);
+        }
+    }
+    endpoints
+}
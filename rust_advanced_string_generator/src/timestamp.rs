@@ -0,0 +1,135 @@
+// The `\T{...}` token: timestamps that either advance from a start by a
+// fixed step or are drawn at random between two bounds.
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, TimeDelta};
+
+const DEFAULT_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TimeSpec {
+    /// The text between the braces, which also keys the sequence in the
+    /// generator state.
+    pub(crate) source: String,
+    pub(crate) start: DateTime<FixedOffset>,
+    /// Set for random timestamps, which are drawn from `start..=end`.
+    pub(crate) end: Option<DateTime<FixedOffset>>,
+    pub(crate) step: TimeDelta,
+    pub(crate) format: String,
+    /// The offset the timestamps are shown in.
+    pub(crate) offset: FixedOffset,
+}
+
+impl TimeSpec {
+    /// Parses `START[..END][,STEP][,tz=OFFSET][,FORMAT]`, where STEP is
+    /// a signed amount such as `+15m` or `-1d12h` and everything after the
+    /// other items is the strftime format, commas included.
+    pub(crate) fn parse(source: &str) -> Option<TimeSpec> {
+        let mut items = source.split(',');
+        let bounds = items.next()?.trim();
+        let (start, end) = match bounds.split_once("..") {
+            Some((start, end)) => (parse_time(start)?, Some(parse_time(end)?)),
+            None => (parse_time(bounds)?, None),
+        };
+        if end.is_some_and(|end| end < start) {
+            return None;
+        }
+        let mut step = TimeDelta::seconds(1);
+        let mut offset = *start.offset();
+        let mut format = Vec::new();
+        for item in items {
+            if !format.is_empty() {
+                format.push(item);
+            } else if let Some(parsed) = parse_step(item.trim()) {
+                step = parsed;
+            } else if let Some(name) = item.trim().strip_prefix("tz=") {
+                offset = parse_offset(name)?;
+            } else {
+                format.push(item);
+            }
+        }
+        let format = if format.is_empty() { DEFAULT_FORMAT.to_string() } else { format.join(",") };
+        if StrftimeItems::new(&format).any(|item| item == Item::Error) {
+            return None;
+        }
+        Some(TimeSpec { source: source.to_string(), start, end, step, format, offset })
+    }
+
+    pub(crate) fn render(&self, time: DateTime<FixedOffset>) -> String {
+        time.with_timezone(&self.offset).format(&self.format).to_string()
+    }
+
+    /// Number of whole seconds between the bounds of a random timestamp.
+    pub(crate) fn span_seconds(&self) -> u64 {
+        self.end.map_or(0, |end| (end - self.start).num_seconds() as u64)
+    }
+}
+
+/// Parses a date or date and time, with an optional `Z` or `+HH:MM` offset;
+/// without one the time is UTC.
+pub(crate) fn parse_time(text: &str) -> Option<DateTime<FixedOffset>> {
+    let text = text.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Some(time);
+    }
+    let (local, offset) = match text.char_indices().skip(10).find(|&(_, c)| matches!(c, 'Z' | '+' | '-')) {
+        Some((i, _)) => (&text[..i], parse_offset(&text[i..])?),
+        None => (text, FixedOffset::east_opt(0)?),
+    };
+    let local = ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(local, format).ok())
+        .or_else(|| NaiveDate::parse_from_str(local, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0))?;
+    local.and_local_timezone(offset).single()
+}
+
+/// Parses `Z`, `UTC` or `+HH:MM`, `-HH:MM`, `+HHMM`, `+HH`.
+fn parse_offset(text: &str) -> Option<FixedOffset> {
+    if text == "Z" || text == "UTC" {
+        return FixedOffset::east_opt(0);
+    }
+    let sign = match text.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let digits: String = text[1..].chars().filter(|&c| c != ':').collect();
+    if !digits.chars().all(|c| c.is_ascii_digit()) || !matches!(digits.len(), 2 | 4) {
+        return None;
+    }
+    let hours: i32 = digits[..2].parse().ok()?;
+    let minutes: i32 = digits.get(2..).map_or(Some(0), |minutes| minutes.parse().ok())?;
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Parses a signed step such as `+15m`, `-1d` or `+1h30m`, with units `s`,
+/// `m`, `h`, `d` and `w`.
+fn parse_step(text: &str) -> Option<TimeDelta> {
+    let sign = match text.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let mut seconds: i64 = 0;
+    let mut number = String::new();
+    for ch in text[1..].chars() {
+        if ch.is_ascii_digit() {
+            number.push(ch);
+            continue;
+        }
+        let unit = match ch {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86_400,
+            'w' => 604_800,
+            _ => return None,
+        };
+        seconds = seconds.checked_add(number.parse::<i64>().ok()?.checked_mul(unit)?)?;
+        number.clear();
+    }
+    if !number.is_empty() || text.len() == 1 {
+        return None;
+    }
+    TimeDelta::try_seconds(sign * seconds)
+}
@@ -1,5 +1,5 @@
 use wasm_bindgen::prelude::*;
-use super::RegexGenerator;
+use super::{parse_table, Clock, Counter, CounterMode, RegexGenerator};
 
 #[wasm_bindgen]
 pub struct WasmRegexGenerator {
@@ -19,8 +19,105 @@ impl WasmRegexGenerator {
         }
     }
 
+    /// Sets named arrays from an object such as
+    /// `{ first: ["Ann", "Bo"], city: ["Oslo", "Rome"] }`, for `\a{first}`
+    /// and `\a{city}`.
+    #[wasm_bindgen]
+    pub fn set_arrays(&mut self, arrays: &js_sys::Object) -> Result<(), JsValue> {
+        for entry in js_sys::Object::entries(arrays).iter() {
+            let entry = js_sys::Array::from(&entry);
+            let name = entry.get(0).as_string().unwrap_or_default();
+            if !js_sys::Array::is_array(&entry.get(1)) {
+                return Err(JsValue::from_str(&format!("array '{}' must be an array of strings", name)));
+            }
+            let values = js_sys::Array::from(&entry.get(1)).iter().filter_map(|value| value.as_string()).collect();
+            self.generator.set_array(&name, values);
+        }
+        Ok(())
+    }
+
+    /// Sets the array `name` to patterns that are generated when picked.
+    #[wasm_bindgen]
+    pub fn set_pattern_array(&mut self, name: &str, patterns: Vec<JsValue>) {
+        let patterns = patterns.into_iter().filter_map(|pattern| pattern.as_string()).collect();
+        self.generator.set_pattern_array(name, patterns);
+    }
+
+    /// Weighs the random picks of the array `name`, one weight per value.
+    #[wasm_bindgen]
+    pub fn set_array_weights(&mut self, name: &str, weights: Vec<f64>) -> Result<(), JsValue> {
+        self.generator.set_array_weights(name, weights).map_err(|err| JsValue::from_str(&err.to_string()))
+    }
+
+    /// Sets the table `name` for `\a{name.column}` from CSV text whose first
+    /// line names the columns.
+    #[wasm_bindgen]
+    pub fn set_table(&mut self, name: &str, csv: &str) -> Result<(), JsValue> {
+        let (columns, rows) = parse_table(csv).map_err(|err| JsValue::from_str(&err.to_string()))?;
+        self.generator.set_table(name, columns, rows).map_err(|err| JsValue::from_str(&err.to_string()))
+    }
+
+    /// Defines the macros of a library of `name = pattern` lines for
+    /// `\m{name}`.
+    #[wasm_bindgen]
+    pub fn load_library(&mut self, text: &str) -> Result<(), JsValue> {
+        self.generator.load_library(text).map_err(|err| JsValue::from_str(&err.to_string()))
+    }
+
+    /// Sets the clock of `\u{uuid7}`, `\u{ulid}` and `\u{snowflake}`:
+    /// `"system"`, milliseconds since 1970 or a time such as
+    /// `"2024-01-01T12:00:00Z"`.
+    #[wasm_bindgen]
+    pub fn set_clock(&mut self, clock: &str) -> Result<(), JsValue> {
+        let clock = Clock::parse(clock).ok_or_else(|| JsValue::from_str("clock must be 'system', milliseconds or a time"))?;
+        self.generator.set_clock(clock);
+        Ok(())
+    }
+
+    /// Adds a counter from a `[NAME=]START[,OPTION...]` spec such as
+    /// `order=1000,desc,width=6,max=9999,wrap`, the same format as the `-i`
+    /// command line option.
+    #[wasm_bindgen]
+    pub fn set_counter(&mut self, spec: &str) -> Result<(), JsValue> {
+        let (name, counter) = Counter::parse(spec).map_err(|err| JsValue::from_str(&err.to_string()))?;
+        self.generator.set_counter(&name, counter);
+        Ok(())
+    }
+
+    /// Sets when counters advance: `per-occurrence` or `per-call`.
+    #[wasm_bindgen]
+    pub fn set_counter_mode(&mut self, mode: &str) -> Result<(), JsValue> {
+        let mode = match mode {
+            "per-occurrence" => CounterMode::PerOccurrence,
+            "per-call" => CounterMode::PerCall,
+            _ => return Err(JsValue::from_str("counter mode must be 'per-occurrence' or 'per-call'")),
+        };
+        self.generator.set_counter_mode(mode);
+        Ok(())
+    }
+
+    /// The generator state as JSON, for `restore_state` to continue from.
+    #[wasm_bindgen]
+    pub fn state(&self) -> String {
+        serde_json::to_string(&self.generator.state()).unwrap_or_default()
+    }
+
+    #[wasm_bindgen]
+    pub fn restore_state(&mut self, json: &str) -> Result<(), JsValue> {
+        let state = serde_json::from_str(json).map_err(|err| JsValue::from_str(&err.to_string()))?;
+        self.generator.restore_state(state);
+        Ok(())
+    }
+
     #[wasm_bindgen]
     pub fn generate(&mut self) -> String {
         self.generator.generate()
     }
+
+    /// Like `generate`, but throws when a counter set to stop at its bound
+    /// runs past it or pattern arrays nest too deeply.
+    #[wasm_bindgen]
+    pub fn try_generate(&mut self) -> Result<String, JsValue> {
+        self.generator.try_generate().map_err(|err| JsValue::from_str(&err.to_string()))
+    }
 }